@@ -0,0 +1,498 @@
+
+use ::std::io::{ self, Read, Write };
+use ::std::error::Error;
+use ::std::ops::Range;
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// The first bytes of every IdVec written by `write_to`
+const MAGIC: [u8; 4] = *b"IDVC";
+
+/// The current version of the format, increased on incompatible changes
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Never pre-allocate more elements than this, as the length may come from corrupted input
+const MAX_PREALLOCATION: usize = 1024;
+
+/// Never accept more unused slots than this, unless there are even more used slots,
+/// as a few corrupted bytes could otherwise allocate a slot for every possible id
+const MAX_UNUSED_SLOTS: usize = 1 << 20;
+
+
+/// Writes a value into the binary format of this crate.
+/// Implemented for primitives, strings, vectors, options and ids.
+pub trait Encode {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()>;
+}
+
+/// Reads a value from the binary format of this crate.
+/// Must read exactly the bytes written by the corresponding `Encode` implementation.
+pub trait Decode: Sized {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError>;
+}
+
+
+/// Returned when reading invalid or incomplete data
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input ended before all data has been read
+    Truncated,
+
+    /// The input does not start with the expected header
+    InvalidHeader,
+
+    /// The input was written by a newer version of this crate
+    UnsupportedVersion(u16),
+
+    /// The elements were written with a version of their type which cannot be migrated
+    UnsupportedElementVersion(u32),
+
+    /// The input contains values which are not allowed
+    InvalidData(&'static str),
+
+    /// The reader failed for other reasons than reaching the end
+    Io(io::Error),
+}
+
+
+/// Write an unsigned integer using as few bytes as possible, 7 bits per byte
+pub(crate) fn write_length<W: Write>(write: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return write.write_all(&[byte]);
+        }
+
+        write.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Read an unsigned integer written by `write_length`
+pub(crate) fn read_length<R: Read>(read: &mut R) -> Result<u64, DecodeError> {
+    let mut value = 0_u64;
+
+    for shift in (0 .. 64).step_by(7) {
+        let byte = u8::decode(read)?;
+        let bits = u64::from(byte & 0x7f);
+
+        if shift == 63 && bits > 1 {
+            return Err(DecodeError::InvalidData("length overflow"));
+        }
+
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(DecodeError::InvalidData("length overflow"))
+}
+
+/// Read a length which must fit into the memory of this machine
+pub(crate) fn read_index<R: Read>(read: &mut R) -> Result<Index, DecodeError> {
+    let length = read_length(read)?;
+    if length > usize::MAX as u64 {
+        return Err(DecodeError::InvalidData("index exceeds the address space"));
+    }
+
+    Ok(length as Index)
+}
+
+
+/// Write the magic bytes and the format version
+pub(crate) fn write_header<W: Write>(write: &mut W, magic: [u8; 4]) -> io::Result<()> {
+    write.write_all(&magic)?;
+    FORMAT_VERSION.encode(write)
+}
+
+/// Read and validate the magic bytes and the format version
+pub(crate) fn read_header<R: Read>(read: &mut R, expected_magic: [u8; 4]) -> Result<(), DecodeError> {
+    let mut magic = [0_u8; 4];
+    read_exact(read, &mut magic)?;
+
+    if magic != expected_magic {
+        return Err(DecodeError::InvalidHeader);
+    }
+
+    let version = u16::decode(read)?;
+    if version != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+fn read_exact<R: Read>(read: &mut R, bytes: &mut [u8]) -> Result<(), DecodeError> {
+    read.read_exact(bytes).map_err(DecodeError::from)
+}
+
+
+
+/// Write the id index limit, followed by the used and unused slots
+/// as alternating run lengths, starting with used slots
+pub(crate) fn write_slots<W: Write, T>(write: &mut W, vec: &IdVec<T>) -> io::Result<()> {
+    write_length(write, vec.id_index_limit() as u64)?;
+
+    let mut runs = Vec::new();
+    let mut used = true;
+    let mut run_length = 0;
+
+    for index in 0 .. vec.id_index_limit() {
+        if vec.contains_id(Id::from_index(index)) != used {
+            runs.push(run_length);
+            used = !used;
+            run_length = 0;
+        }
+
+        run_length += 1;
+    }
+
+    if run_length != 0 { runs.push(run_length); }
+
+    write_length(write, runs.len() as u64)?;
+    for run in runs {
+        write_length(write, run as u64)?;
+    }
+
+    Ok(())
+}
+
+/// Fail if a vector with this id index limit would contain too many unused slots.
+/// Vectors with more than a million unused slots, and more unused than used slots, must be packed before writing.
+pub(crate) fn check_unused_slots(id_index_limit: Index, used_count: usize) -> Result<(), DecodeError> {
+    debug_assert!(used_count <= id_index_limit, "More slots are used than exist");
+
+    if id_index_limit - used_count > MAX_UNUSED_SLOTS.max(used_count) {
+        return Err(DecodeError::InvalidData("too many unused slots"));
+    }
+
+    Ok(())
+}
+
+/// Fail before writing a vector which `check_unused_slots` would reject when reading
+pub(crate) fn check_writable_slots<T>(vec: &IdVec<T>) -> io::Result<()> {
+    check_unused_slots(vec.id_index_limit(), vec.len()).map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidInput, "too many unused slots to be read again, pack the vector before writing"
+    ))
+}
+
+/// Read the slots written by `write_slots`, returning the used slots as ranges of indices
+pub(crate) fn read_slots<R: Read>(read: &mut R) -> Result<Vec<Range<Index>>, DecodeError> {
+    let id_index_limit = read_index(read)?;
+    let run_count = read_index(read)?;
+
+    let mut used_ranges = Vec::with_capacity(run_count.min(MAX_PREALLOCATION));
+    let mut total = 0_usize;
+    let mut used_count = 0_usize;
+
+    for run_index in 0 .. run_count {
+        let run = read_index(read)?;
+        let start = total;
+
+        total = total.checked_add(run)
+            .filter(|&total| total <= id_index_limit)
+            .ok_or(DecodeError::InvalidData("slot runs exceed the id index limit"))?;
+
+        if run_index % 2 == 0 {
+            used_ranges.push(start .. total);
+            used_count += run;
+        }
+    }
+
+    if total != id_index_limit {
+        return Err(DecodeError::InvalidData("slot runs do not cover the id index limit"));
+    }
+
+    check_unused_slots(id_index_limit, used_count)?;
+
+    Ok(used_ranges)
+}
+
+
+
+impl<T> IdVec<T> where T: Encode {
+
+    /// Write all ids and elements into the binary format of this crate.
+    /// The format consists of a versioned header, the used and unused slots
+    /// as alternating run lengths starting with used slots, and all elements ordered by id.
+    /// Fails without writing if there are more than a million unused slots, and more unused than used slots,
+    /// as reading rejects such vectors. Pack the vector before writing in that case.
+    pub fn write_to<W: Write>(&self, mut write: W) -> io::Result<()> {
+        check_writable_slots(self)?;
+        write_header(&mut write, MAGIC)?;
+        write_slots(&mut write, self)?;
+
+        for element in self.elements() {
+            element.encode(&mut write)?;
+        }
+
+        write.flush()
+    }
+}
+
+impl<T> IdVec<T> where T: Decode {
+
+    /// Read ids and elements which were written by `write_to`.
+    /// The ids of all elements are identical to the written vector.
+    pub fn read_from<R: Read>(mut read: R) -> Result<Self, DecodeError> {
+        read_header(&mut read, MAGIC)?;
+        let used_ranges = read_slots(&mut read)?;
+        let mut vec = IdVec::new();
+
+        for index in used_ranges.into_iter().flatten() {
+            vec.insert_at(Id::from_index(index), T::decode(&mut read)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+
+
+macro_rules! impl_number {
+    ( $($number:ty),* ) => { $(
+        impl Encode for $number {
+            fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+                write.write_all(&self.to_le_bytes())
+            }
+        }
+
+        impl Decode for $number {
+            fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+                let mut bytes = [0_u8; ::std::mem::size_of::<$number>()];
+                read_exact(read, &mut bytes)?;
+                Ok(<$number>::from_le_bytes(bytes))
+            }
+        }
+    )* };
+}
+
+impl_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        (*self as u8).encode(write)
+    }
+}
+
+impl Decode for bool {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+        match u8::decode(read)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidData("invalid bool")),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write_length(write, self.len() as u64)?;
+        write.write_all(self.as_bytes())
+    }
+}
+
+impl Decode for String {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+        let bytes = Vec::<u8>::decode(read)?;
+        String::from_utf8(bytes).map_err(|_| DecodeError::InvalidData("invalid utf-8 string"))
+    }
+}
+
+impl<T> Encode for Vec<T> where T: Encode {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write_length(write, self.len() as u64)?;
+        for element in self {
+            element.encode(write)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Decode for Vec<T> where T: Decode {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+        let length = read_index(read)?;
+        let mut vec = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+
+        for _ in 0 .. length {
+            vec.push(T::decode(read)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+impl<T> Encode for Option<T> where T: Encode {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        match *self {
+            Some(ref value) => { true.encode(write)?; value.encode(write) },
+            None => false.encode(write),
+        }
+    }
+}
+
+impl<T> Decode for Option<T> where T: Decode {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+        if bool::decode(read)? { T::decode(read).map(Some) } else { Ok(None) }
+    }
+}
+
+/// Encoded as its index
+impl<T> Encode for Id<T> {
+    fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write_length(write, self.index_value() as u64)
+    }
+}
+
+impl<T> Decode for Id<T> {
+    fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+        read_index(read).map(Id::from_index)
+    }
+}
+
+
+
+impl From<io::Error> for DecodeError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof { DecodeError::Truncated }
+        else { DecodeError::Io(error) }
+    }
+}
+
+impl ::std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            DecodeError::Truncated => write!(f, "unexpected end of input"),
+            DecodeError::InvalidHeader => write!(f, "input is not an encoded IdVec"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            DecodeError::UnsupportedElementVersion(version) => write!(f, "no migration from element version {}", version),
+            DecodeError::InvalidData(reason) => write!(f, "invalid data: {}", reason),
+            DecodeError::Io(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            DecodeError::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Node { name: String, parent: Option<Id<Node>>, weight: f32 }
+
+    impl Encode for Node {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.name.encode(write)?;
+            self.parent.encode(write)?;
+            self.weight.encode(write)
+        }
+    }
+
+    impl Decode for Node {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(Node { name: String::decode(read)?, parent: Decode::decode(read)?, weight: f32::decode(read)? })
+        }
+    }
+
+    fn nodes() -> IdVec<Node> {
+        let mut nodes = IdVec::new();
+        let root = nodes.insert(Node { name: String::from("root"), parent: None, weight: 1.0 });
+        let removed = nodes.insert(Node { name: String::from("removed"), parent: Some(root), weight: 2.0 });
+        nodes.insert(Node { name: String::from("leaf"), parent: Some(root), weight: 3.0 });
+        nodes.remove(removed);
+        nodes
+    }
+
+    #[test]
+    pub fn test_round_trip_keeps_ids(){
+        let mut bytes = Vec::new();
+        nodes().write_to(&mut bytes).unwrap();
+
+        let decoded = IdVec::<Node>::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, nodes());
+        assert_eq!(decoded.id_index_limit(), 3);
+        assert!(!decoded.contains_id(Id::from_index(1)));
+
+        let mut bytes = Vec::new();
+        IdVec::<Node>::new().write_to(&mut bytes).unwrap();
+        assert!(IdVec::<Node>::read_from(bytes.as_slice()).unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn test_lengths(){
+        for &value in &[0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX] {
+            let mut bytes = Vec::new();
+            write_length(&mut bytes, value).unwrap();
+            assert_eq!(read_length(&mut bytes.as_slice()).unwrap(), value);
+        }
+
+        let overflow = [0xff_u8; 11];
+        assert!(read_length(&mut &overflow[..]).is_err());
+    }
+
+    #[test]
+    pub fn test_invalid_input(){
+        let mut bytes = Vec::new();
+        nodes().write_to(&mut bytes).unwrap();
+
+        for length in 0 .. bytes.len() {
+            match IdVec::<Node>::read_from(&bytes[.. length]) {
+                Err(DecodeError::Truncated) => {},
+                other => panic!("expected truncation error at length {}, got {:?}", length, other),
+            }
+        }
+
+        assert!(matches!(IdVec::<Node>::read_from(&b"IDVX\x01\x00"[..]), Err(DecodeError::InvalidHeader)));
+        assert!(matches!(IdVec::<Node>::read_from(&b"IDVC\x02\x00"[..]), Err(DecodeError::UnsupportedVersion(2))));
+
+        // an id index limit of 3, but runs of 1 used and 1 unused slot
+        let invalid_runs = b"IDVC\x01\x00\x03\x02\x01\x01";
+        assert!(matches!(IdVec::<Node>::read_from(&invalid_runs[..]), Err(DecodeError::InvalidData(_))));
+    }
+
+    #[test]
+    pub fn test_unused_slot_limit(){
+        let mut vec = IdVec::new();
+        vec.insert_at(Id::from_index(MAX_UNUSED_SLOTS), 7_u8);
+
+        let mut bytes = Vec::new();
+        vec.write_to(&mut bytes).unwrap();
+        let decoded = IdVec::<u8>::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.id_index_limit(), MAX_UNUSED_SLOTS + 1, "exactly at the limit");
+        assert_eq!(decoded.iter().collect::<Vec<_>>(), vec![ (Id::from_index(MAX_UNUSED_SLOTS), &7) ]);
+
+        let mut vec = IdVec::new();
+        vec.insert_at(Id::from_index(MAX_UNUSED_SLOTS + 1), 7_u8);
+
+        let mut bytes = Vec::new();
+        let error = vec.write_to(&mut bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(bytes.is_empty(), "nothing is written");
+    }
+
+    #[test]
+    pub fn test_huge_unused_run(){
+        // an id index limit of 2^62, with runs of 0 used, 2^62 - 1 unused, and 1 used slot
+        let mut hostile = b"IDVC\x01\x00".to_vec();
+        for &length in &[1_u64 << 62, 3, 0, (1 << 62) - 1, 1] {
+            write_length(&mut hostile, length).unwrap();
+        }
+
+        hostile.extend_from_slice(&[0, 0, 0, 0, 0]);
+        assert!(matches!(IdVec::<Node>::read_from(hostile.as_slice()), Err(DecodeError::InvalidData(_))));
+    }
+}
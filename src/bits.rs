@@ -0,0 +1,68 @@
+
+use ::id::Index;
+
+
+/// A fixed-size set of indices, using a single bit per index.
+/// Used to mark ids while visiting the elements of an `IdVec`,
+/// sized by `IdVec::id_index_limit()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+const WORD_BITS: usize = 64;
+
+impl BitSet {
+
+    /// Create a set which can contain all indices below `limit`
+    pub fn new(limit: usize) -> Self {
+        BitSet { words: vec![0; limit.div_ceil(WORD_BITS)] }
+    }
+
+    /// Returns false for indices out of range
+    pub fn contains(&self, index: Index) -> bool {
+        self.words.get(index / WORD_BITS)
+            .is_some_and(|word| word & (1 << (index % WORD_BITS)) != 0)
+    }
+
+    /// Returns whether the index was newly inserted.
+    /// Panics on indices out of range.
+    pub fn insert(&mut self, index: Index) -> bool {
+        let word = &mut self.words[index / WORD_BITS];
+        let bit = 1 << (index % WORD_BITS);
+        let inserted = *word & bit == 0;
+        *word |= bit;
+        inserted
+    }
+
+    /// Ignores indices out of range
+    pub fn remove(&mut self, index: Index) {
+        if let Some(word) = self.words.get_mut(index / WORD_BITS) {
+            *word &= !(1 << (index % WORD_BITS));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_insert_and_remove(){
+        let mut set = BitSet::new(130);
+        assert!(!set.contains(129));
+        assert!(!set.contains(4000), "out of range index");
+
+        assert!(set.insert(129));
+        assert!(!set.insert(129), "inserting twice");
+        assert!(set.insert(0));
+        assert!(set.contains(129));
+        assert!(set.contains(0));
+        assert!(!set.contains(64));
+
+        set.remove(129);
+        assert!(!set.contains(129));
+        assert!(set.contains(0));
+    }
+}
@@ -0,0 +1,443 @@
+
+use ::std::any::{ Any, TypeId };
+use ::std::collections::HashMap;
+use ::std::io::{ self, Read, Write };
+use ::id::*;
+use ::vec::IdVec;
+use ::bits::BitSet;
+use ::binary::{ Encode, Decode, DecodeError, write_header, read_header, write_length, read_index };
+
+
+/// The first bytes of every bundle written by `Bundle::save`
+const MAGIC: [u8; 4] = *b"IDVB";
+
+
+/// Implemented by elements which store ids of elements in other members of a `Bundle`, or in the same member.
+/// Enables rewriting the ids when the members are packed, and detecting dangling ids when loading.
+/// Both methods must visit the same ids.
+pub trait CrossReferences {
+
+    /// Call `visitor.visit` for every id stored inside this element
+    fn visit_ids<V: IdVisitor>(&self, visitor: &mut V);
+
+    /// Call `visitor.visit_mut` for every id stored inside this element
+    fn visit_ids_mut<V: IdVisitorMut>(&mut self, visitor: &mut V);
+}
+
+/// Receives the ids stored inside an element
+pub trait IdVisitor {
+    fn visit<T: 'static>(&mut self, id: Id<T>);
+}
+
+/// Receives the ids stored inside an element, and may replace them
+pub trait IdVisitorMut {
+    fn visit_mut<T: 'static>(&mut self, id: &mut Id<T>);
+}
+
+
+/// Saves and loads several IdVecs whose elements reference each other.
+/// Ids of element types which are not part of the bundle are neither rewritten nor checked.
+/// Members are saved in the order they were added, and must be added in the same order for loading.
+/// Example: `Bundle::new().with(&mut nodes).with(&mut edges).pack_and_save(file)`
+pub struct Bundle<'s> {
+    members: Vec<&'s mut dyn Member>,
+}
+
+
+/// A type-erased `IdVec` inside a bundle
+trait Member {
+    fn element_type(&self) -> TypeId;
+    fn occupancy(&self) -> BitSet;
+    fn pack_ids(&mut self) -> HashMap<Index, Index>;
+    fn remap(&mut self, moved: &HashMap<TypeId, HashMap<Index, Index>>);
+    fn contains_dangling(&self, occupancy: &HashMap<TypeId, BitSet>) -> bool;
+    fn write(&self, write: &mut dyn Write) -> io::Result<()>;
+
+    /// Read a new vector of the same type, without modifying this vector
+    fn read(&self, read: &mut dyn Read) -> Result<Box<dyn Member>, DecodeError>;
+
+    /// Replace this vector with a vector returned by `read`
+    fn replace(&mut self, loaded: Box<dyn Member>);
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+
+/// Returns if any element contains an id which does not point to any element of these members
+fn contains_dangling<'m, M>(members: M) -> bool where M: Iterator<Item = &'m dyn Member> + Clone {
+    let occupancy: HashMap<TypeId, BitSet> = members.clone()
+        .map(|member| (member.element_type(), member.occupancy()))
+        .collect();
+
+    members.clone().any(|member| member.contains_dangling(&occupancy))
+}
+
+
+/// Replaces ids of moved elements with their new id
+struct Remap<'m> {
+    moved: &'m HashMap<TypeId, HashMap<Index, Index>>,
+
+    /// Whether any visited id points to a moved element
+    found: bool,
+}
+
+impl<'m> Remap<'m> {
+    fn new_index<T: 'static>(&self, id: Id<T>) -> Option<Index> {
+        self.moved.get(&TypeId::of::<T>())
+            .and_then(|moved| moved.get(&id.index_value()))
+            .cloned()
+    }
+}
+
+impl<'m> IdVisitor for Remap<'m> {
+    fn visit<T: 'static>(&mut self, id: Id<T>) {
+        self.found |= self.new_index(id).is_some();
+    }
+}
+
+impl<'m> IdVisitorMut for Remap<'m> {
+    fn visit_mut<T: 'static>(&mut self, id: &mut Id<T>) {
+        if let Some(new_index) = self.new_index(*id) {
+            *id = Id::from_index(new_index);
+        }
+    }
+}
+
+/// Detects ids which do not point to any element of the bundle
+struct FindDangling<'o> {
+    occupancy: &'o HashMap<TypeId, BitSet>,
+    found: bool,
+}
+
+impl<'o> IdVisitor for FindDangling<'o> {
+    fn visit<T: 'static>(&mut self, id: Id<T>) {
+        if let Some(occupancy) = self.occupancy.get(&TypeId::of::<T>()) {
+            self.found |= !occupancy.contains(id.index_value());
+        }
+    }
+}
+
+
+impl<T> Member for IdVec<T> where T: Encode + Decode + CrossReferences + 'static {
+    fn element_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn occupancy(&self) -> BitSet {
+        let mut occupancy = BitSet::new(self.id_index_limit());
+        for id in self.ids() {
+            occupancy.insert(id.index_value());
+        }
+
+        occupancy
+    }
+
+    fn pack_ids(&mut self) -> HashMap<Index, Index> {
+        let mut moved = HashMap::new();
+        self.pack(|old_id, new_id| { moved.insert(old_id.index_value(), new_id.index_value()); });
+        moved
+    }
+
+    fn remap(&mut self, moved: &HashMap<TypeId, HashMap<Index, Index>>) {
+        let mut remap = Remap { moved, found: false };
+
+        // only mutably access elements which need to be rewritten, as mutable access is tracked
+        let referencing_moved: Vec<Id<T>> = self.iter()
+            .filter(|&(_, element)| {
+                remap.found = false;
+                element.visit_ids(&mut remap);
+                remap.found
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        for id in referencing_moved {
+            self[id].visit_ids_mut(&mut remap);
+        }
+    }
+
+    fn contains_dangling(&self, occupancy: &HashMap<TypeId, BitSet>) -> bool {
+        let mut find_dangling = FindDangling { occupancy, found: false };
+        for element in self.elements() {
+            element.visit_ids(&mut find_dangling);
+        }
+
+        find_dangling.found
+    }
+
+    fn write(&self, write: &mut dyn Write) -> io::Result<()> {
+        self.write_to(write)
+    }
+
+    fn read(&self, read: &mut dyn Read) -> Result<Box<dyn Member>, DecodeError> {
+        Ok(Box::new(IdVec::<T>::read_from(read)?))
+    }
+
+    fn replace(&mut self, loaded: Box<dyn Member>) {
+        *self = *loaded.into_any().downcast().expect("Loaded bundle member has a different type");
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+
+impl<'s> Bundle<'s> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        Bundle { members: Vec::new() }
+    }
+
+    /// Add a member. Panics if the bundle already contains a vector of the same element type.
+    pub fn with<T>(mut self, vec: &'s mut IdVec<T>) -> Self where T: Encode + Decode + CrossReferences + 'static {
+        assert!(
+            self.members.iter().all(|member| member.element_type() != TypeId::of::<T>()),
+            "Bundle contains multiple vectors of the same element type"
+        );
+
+        self.members.push(vec);
+        self
+    }
+
+    /// Returns if any element contains an id which does not point to any element of the bundle
+    pub fn contains_dangling_references(&self) -> bool {
+        contains_dangling(self.members.iter().map(|member| &**member as &dyn Member))
+    }
+
+    /// Pack all members, and rewrite all ids stored inside the elements to the new ids.
+    /// Panics in debug builds if any element contains a dangling id,
+    /// as it may point to a moved element afterwards.
+    pub fn pack(&mut self) {
+        debug_assert!(!self.contains_dangling_references(), "Packing bundle with dangling references");
+
+        let moved: HashMap<TypeId, HashMap<Index, Index>> = self.members.iter_mut()
+            .map(|member| (member.element_type(), member.pack_ids()))
+            .collect();
+
+        for member in &mut self.members {
+            member.remap(&moved);
+        }
+    }
+
+    fn check_dangling_references(&self) -> io::Result<()> {
+        if self.contains_dangling_references() {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "bundle contains dangling references"))
+        } else { Ok(()) }
+    }
+
+    fn write_members<W: Write>(&self, mut write: W) -> io::Result<()> {
+        write_header(&mut write, MAGIC)?;
+        write_length(&mut write, self.members.len() as u64)?;
+
+        for member in &self.members {
+            member.write(&mut write)?;
+        }
+
+        write.flush()
+    }
+
+    /// Write all members. Fails without writing if any element contains a dangling id.
+    pub fn save<W: Write>(&self, write: W) -> io::Result<()> {
+        self.check_dangling_references()?;
+        self.write_members(write)
+    }
+
+    /// Pack all members in memory, rewriting all stored ids, and then write them.
+    /// The written vectors do not contain any unused slots.
+    /// Fails without modifying or writing if any element contains a dangling id.
+    pub fn pack_and_save<W: Write>(&mut self, write: W) -> io::Result<()> {
+        self.check_dangling_references()?;
+        self.pack();
+        self.write_members(write)
+    }
+
+    /// Replace the contents of all members with the saved vectors.
+    /// Fails without modifying any member if the number of members does not match,
+    /// or if any element contains a dangling id.
+    pub fn load<R: Read>(&mut self, mut read: R) -> Result<(), DecodeError> {
+        read_header(&mut read, MAGIC)?;
+
+        if read_index(&mut read)? != self.members.len() {
+            return Err(DecodeError::InvalidData("number of bundle members does not match"));
+        }
+
+        let loaded = self.members.iter()
+            .map(|member| member.read(&mut read))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if contains_dangling(loaded.iter().map(|member| &**member)) {
+            return Err(DecodeError::InvalidData("bundle contains dangling references"));
+        }
+
+        for (member, loaded) in self.members.iter_mut().zip(loaded) {
+            member.replace(loaded);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'s> Default for Bundle<'s> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Node { name: String, parent: Option<Id<Node>> }
+
+    #[derive(Debug, PartialEq)]
+    struct Edge { from: Id<Node>, to: Id<Node> }
+
+    impl Encode for Node {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.name.encode(write)?;
+            self.parent.encode(write)
+        }
+    }
+
+    impl Decode for Node {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(Node { name: String::decode(read)?, parent: Decode::decode(read)? })
+        }
+    }
+
+    impl CrossReferences for Node {
+        fn visit_ids<V: IdVisitor>(&self, visitor: &mut V) {
+            if let Some(parent) = self.parent {
+                visitor.visit(parent);
+            }
+        }
+
+        fn visit_ids_mut<V: IdVisitorMut>(&mut self, visitor: &mut V) {
+            if let Some(ref mut parent) = self.parent {
+                visitor.visit_mut(parent);
+            }
+        }
+    }
+
+    impl Encode for Edge {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.from.encode(write)?;
+            self.to.encode(write)
+        }
+    }
+
+    impl Decode for Edge {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(Edge { from: Id::decode(read)?, to: Id::decode(read)? })
+        }
+    }
+
+    impl CrossReferences for Edge {
+        fn visit_ids<V: IdVisitor>(&self, visitor: &mut V) {
+            visitor.visit(self.from);
+            visitor.visit(self.to);
+        }
+
+        fn visit_ids_mut<V: IdVisitorMut>(&mut self, visitor: &mut V) {
+            visitor.visit_mut(&mut self.from);
+            visitor.visit_mut(&mut self.to);
+        }
+    }
+
+    fn node(name: &str, parent: Option<Id<Node>>) -> Node {
+        Node { name: name.to_owned(), parent }
+    }
+
+    fn name_of(nodes: &IdVec<Node>, id: Id<Node>) -> &str {
+        &nodes[id].name
+    }
+
+    #[test]
+    pub fn test_pack_and_save_rewrites_references(){
+        let mut nodes = IdVec::new();
+        let removed = nodes.insert(node("removed", None));
+        let root = nodes.insert(node("root", None));
+        let leaf = nodes.insert(node("leaf", Some(root)));
+        let last = nodes.insert(node("last", None));
+        nodes.remove(removed);
+
+        let mut edges = id_vec!(Edge { from: root, to: leaf }, Edge { from: last, to: root });
+
+        edges.track_changes();
+        Bundle::new().with(&mut nodes).with(&mut edges).save(Vec::new()).unwrap();
+        assert!(edges.take_changes().is_empty(), "saving does not modify");
+
+        let mut bytes = Vec::new();
+        Bundle::new().with(&mut nodes).with(&mut edges).pack_and_save(&mut bytes).unwrap();
+        assert!(nodes.is_packed());
+
+        let modified = edges.take_changes().modified;
+        assert_eq!(modified.into_iter().collect::<Vec<_>>(), vec![Id::from_index(1)], "only the edge from the moved node");
+
+        let mut loaded_nodes: IdVec<Node> = IdVec::new();
+        let mut loaded_edges: IdVec<Edge> = IdVec::new();
+        Bundle::new().with(&mut loaded_nodes).with(&mut loaded_edges).load(bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded_nodes.len(), 3);
+        assert!(loaded_nodes.is_packed());
+
+        assert_eq!(name_of(&loaded_nodes, loaded_edges[Id::from_index(1)].from), "last");
+
+        let edge = &loaded_edges[Id::from_index(0)];
+        assert_eq!(name_of(&loaded_nodes, edge.from), "root");
+        assert_eq!(name_of(&loaded_nodes, edge.to), "leaf");
+        assert_eq!(loaded_nodes[edge.to].parent, Some(edge.from));
+    }
+
+    #[test]
+    pub fn test_dangling_references(){
+        let mut nodes = id_vec!(node("root", None));
+        let mut edges = id_vec!(Edge { from: Id::from_index(0), to: Id::from_index(1) });
+        assert!(Bundle::new().with(&mut nodes).with(&mut edges).save(Vec::new()).is_err());
+
+        // the edges reference nodes which are not part of the saved bundle
+        let mut bytes = Vec::new();
+        Bundle::new().with(&mut edges).save(&mut bytes).unwrap();
+
+        let mut loaded_nodes: IdVec<Node> = IdVec::new();
+        let mut loaded_edges: IdVec<Edge> = IdVec::new();
+        let result = Bundle::new().with(&mut loaded_nodes).with(&mut loaded_edges).load(bytes.as_slice());
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))), "member count mismatch");
+
+        // bypass the validation of `save`
+        let mut fake_bundle = Vec::new();
+        write_header(&mut fake_bundle, MAGIC).unwrap();
+        write_length(&mut fake_bundle, 2).unwrap();
+        nodes.write_to(&mut fake_bundle).unwrap();
+        edges.write_to(&mut fake_bundle).unwrap();
+
+        let result = Bundle::new().with(&mut loaded_nodes).with(&mut loaded_edges).load(fake_bundle.as_slice());
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))), "edge points to missing node");
+        assert!(loaded_nodes.is_empty() && loaded_edges.is_empty(), "failed loading does not modify");
+    }
+
+    #[test]
+    pub fn test_failed_load_keeps_members(){
+        let mut nodes = id_vec!(node("root", None));
+        let mut edges = id_vec!(Edge { from: Id::from_index(0), to: Id::from_index(0) });
+
+        let mut bytes = Vec::new();
+        Bundle::new().with(&mut nodes).with(&mut edges).save(&mut bytes).unwrap();
+
+        let mut existing_nodes = id_vec!(node("a", None), node("b", None));
+        let mut existing_edges: IdVec<Edge> = IdVec::new();
+
+        let truncated = &bytes[.. bytes.len() - 1];
+        let result = Bundle::new().with(&mut existing_nodes).with(&mut existing_edges).load(truncated);
+        assert!(matches!(result, Err(DecodeError::Truncated)));
+        assert_eq!(existing_nodes, id_vec!(node("a", None), node("b", None)), "first member is not replaced");
+
+        Bundle::new().with(&mut existing_nodes).with(&mut existing_edges).load(bytes.as_slice()).unwrap();
+        assert_eq!(existing_nodes, nodes);
+        assert_eq!(existing_edges, edges);
+    }
+}
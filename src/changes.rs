@@ -0,0 +1,84 @@
+
+use ::std::collections::HashSet;
+use ::id::*;
+
+
+/// The ids of an `IdVec` which have been inserted, removed or mutably accessed since the last tick.
+/// Changes are combined: An element that is inserted and removed again during the same tick
+/// does not appear at all, and an element that is removed and then replaced by a new element
+/// with the same id appears as modified.
+// manually implementing clone, debug, eq, because T does not need to implement them
+pub struct Changes<T> {
+    pub inserted: HashSet<Id<T>>,
+    pub removed: HashSet<Id<T>>,
+    pub modified: HashSet<Id<T>>,
+}
+
+
+impl<T> Changes<T> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        Changes { inserted: HashSet::new(), removed: HashSet::new(), modified: HashSet::new() }
+    }
+
+    /// Returns if no ids have changed
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    pub(crate) fn record_insert(&mut self, id: Id<T>) {
+        if self.removed.remove(&id) {
+            self.modified.insert(id); // the id existed at the last tick, but points to a new element now
+        } else {
+            self.inserted.insert(id);
+        }
+    }
+
+    pub(crate) fn record_remove(&mut self, id: Id<T>) {
+        self.modified.remove(&id);
+
+        // elements that did not exist at the last tick do not need to be removed
+        if !self.inserted.remove(&id) {
+            self.removed.insert(id);
+        }
+    }
+
+    pub(crate) fn record_modify(&mut self, id: Id<T>) {
+        if !self.inserted.contains(&id) {
+            self.modified.insert(id);
+        }
+    }
+}
+
+impl<T> Default for Changes<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Changes<T> {
+    fn clone(&self) -> Self {
+        Changes {
+            inserted: self.inserted.clone(),
+            removed: self.removed.clone(),
+            modified: self.modified.clone(),
+        }
+    }
+}
+
+impl<T> Eq for Changes<T> {}
+impl<T> PartialEq for Changes<T> {
+    fn eq(&self, other: &Changes<T>) -> bool {
+        self.inserted == other.inserted && self.removed == other.removed && self.modified == other.modified
+    }
+}
+
+impl<T> ::std::fmt::Debug for Changes<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(
+            f, "Changes {{ inserted: {:?}, removed: {:?}, modified: {:?} }}",
+            self.inserted, self.removed, self.modified
+        )
+    }
+}
@@ -0,0 +1,419 @@
+
+use ::std::fs::{ self, File, OpenOptions };
+use ::std::io::{ self, Read, Write, BufReader, BufWriter };
+use ::std::path::{ Path, PathBuf };
+use ::id::*;
+use ::vec::IdVec;
+use ::binary::{ Encode, Decode, DecodeError, write_length, read_index };
+
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const TEMPORARY_CHECKPOINT_FILE: &str = "checkpoint.tmp";
+const LOG_FILE: &str = "log";
+
+/// The number of logged modifications after which a checkpoint is written by default
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 1024;
+
+const INSERT: u8 = 0;
+const REMOVE: u8 = 1;
+const UPDATE: u8 = 2;
+
+
+/// An `IdVec` which persists every modification in a directory, surviving crashes.
+/// Each modification is appended to a log file and synced to disk before it is applied.
+/// Periodically, the whole vector is written to a checkpoint file, and the log is cleared.
+/// A failed periodic checkpoint does not fail the modification, which is already logged,
+/// but is retried after the next modification and reported by `checkpoint_error`.
+///
+/// When opening, the log is replayed over the last checkpoint.
+/// A torn record at the end of the log, written during a crash, is detected by its checksum and discarded.
+pub struct DurableIdVec<T> {
+    elements: IdVec<T>,
+    directory: PathBuf,
+    log: File,
+
+    /// The length of the log after the last complete record
+    log_length: u64,
+
+    /// Set if a failed write could not be removed from the log, until the next checkpoint clears the log.
+    /// Further records would follow the torn record, and would be discarded when opening.
+    poisoned: bool,
+
+    /// The sequence number of the newest logged modification
+    sequence: u64,
+
+    records_since_checkpoint: usize,
+    checkpoint_interval: usize,
+
+    /// The error of the last periodic checkpoint, until a checkpoint succeeds
+    checkpoint_error: Option<io::Error>,
+}
+
+
+/// FNV-1a, used to detect partially written records
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5_u32, |hash, &byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193))
+}
+
+/// Persist the entries of the directory, such as created and renamed files.
+/// Not supported by the standard library on other platforms than unix.
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    if cfg!(unix) { File::open(directory)?.sync_all() }
+    else { Ok(()) }
+}
+
+/// Read the next record of the log.
+/// Returns `None` at the end of the log, and for torn or corrupted records.
+fn read_record<R: Read>(read: &mut R) -> Option<Vec<u8>> {
+    let mut header = [0_u8; 8];
+    read.read_exact(&mut header).ok()?;
+
+    let length = u32::decode(&mut &header[.. 4]).ok()? as usize;
+    let expected_checksum = u32::decode(&mut &header[4 ..]).ok()?;
+
+    let mut payload = Vec::new();
+    read.take(length as u64).read_to_end(&mut payload).ok()?;
+
+    if payload.len() == length && checksum(&payload) == expected_checksum { Some(payload) }
+    else { None }
+}
+
+
+impl<T> DurableIdVec<T> where T: Encode + Decode {
+
+    /// Open or create the vector inside the directory, recovering the state before the last crash.
+    /// Writes a checkpoint after every `DEFAULT_CHECKPOINT_INTERVAL` modifications.
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, DecodeError> {
+        Self::open_with_checkpoint_interval(directory, DEFAULT_CHECKPOINT_INTERVAL)
+    }
+
+    /// Open or create the vector inside the directory, recovering the state before the last crash.
+    /// Writes a checkpoint after the specified number of modifications.
+    pub fn open_with_checkpoint_interval<P: AsRef<Path>>(directory: P, checkpoint_interval: usize) -> Result<Self, DecodeError> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        let (mut sequence, mut elements) = match File::open(directory.join(CHECKPOINT_FILE)) {
+            Ok(file) => {
+                let mut read = BufReader::new(file);
+                (u64::decode(&mut read)?, IdVec::read_from(read)?)
+            },
+
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => (0, IdVec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let log_path = directory.join(LOG_FILE);
+        let mut valid_length = 0;
+        let mut records_since_checkpoint = 0;
+
+        if let Ok(file) = File::open(&log_path) {
+            let mut read = BufReader::new(file);
+
+            while let Some(payload) = read_record(&mut read) {
+                let record_sequence = Self::replay(&mut elements, &payload, sequence)?;
+                sequence = sequence.max(record_sequence);
+                valid_length += 8 + payload.len() as u64;
+                records_since_checkpoint += 1;
+            }
+        }
+
+        let log = OpenOptions::new().create(true).append(true).open(&log_path)?;
+        log.set_len(valid_length)?; // discard the torn record
+        log.sync_all()?;
+        sync_directory(&directory)?; // persist a newly created log
+
+        Ok(DurableIdVec {
+            elements, directory, log, log_length: valid_length, poisoned: false,
+            sequence, records_since_checkpoint, checkpoint_interval, checkpoint_error: None
+        })
+    }
+
+    /// Apply a logged modification, unless it is already contained in the checkpoint.
+    /// Returns the sequence number of the record.
+    fn replay(elements: &mut IdVec<T>, payload: &[u8], checkpoint_sequence: u64) -> Result<u64, DecodeError> {
+        let mut read = payload;
+        let sequence = u64::decode(&mut read)?;
+
+        if sequence > checkpoint_sequence {
+            let operation = u8::decode(&mut read)?;
+            let id = Id::from_index(read_index(&mut read)?);
+
+            match operation {
+                INSERT | UPDATE => { elements.insert_at(id, T::decode(&mut read)?); },
+                REMOVE => { elements.remove(id); },
+                _ => return Err(DecodeError::InvalidData("unknown log operation")),
+            }
+        }
+
+        Ok(sequence)
+    }
+
+    /// Append a modification to the log and sync it to disk.
+    /// On failure, the partially written record is truncated, or the vector refuses all further modifications.
+    fn log(&mut self, operation: u8, id: Id<T>, element: Option<&T>) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other("log contains a torn record, write a checkpoint to continue"));
+        }
+
+        let mut payload = Vec::new();
+        (self.sequence + 1).encode(&mut payload)?;
+        operation.encode(&mut payload)?;
+        write_length(&mut payload, id.index_value() as u64)?;
+
+        if let Some(element) = element {
+            element.encode(&mut payload)?;
+        }
+
+        let mut record = Vec::with_capacity(8 + payload.len());
+        (payload.len() as u32).encode(&mut record)?;
+        checksum(&payload).encode(&mut record)?;
+        record.extend_from_slice(&payload);
+
+        let written = self.log.write_all(&record).and_then(|_| self.log.sync_data());
+
+        if let Err(error) = written {
+            let truncated = self.log.set_len(self.log_length).and_then(|_| self.log.sync_data());
+            self.poisoned = truncated.is_err();
+            return Err(error);
+        }
+
+        self.log_length += record.len() as u64;
+        self.sequence += 1;
+        self.records_since_checkpoint += 1;
+        Ok(())
+    }
+
+    /// Write a checkpoint if enough modifications have been logged since the last one.
+    /// Remembers a failure instead of returning it, as the modification itself has already succeeded.
+    fn checkpoint_if_due(&mut self) {
+        if self.records_since_checkpoint >= self.checkpoint_interval {
+            if let Err(error) = self.checkpoint() {
+                self.checkpoint_error = Some(error);
+            }
+        }
+    }
+
+    /// The error of the last periodic checkpoint, if no checkpoint has succeeded since.
+    /// The log keeps growing until a checkpoint succeeds, but no modification is lost.
+    pub fn checkpoint_error(&self) -> Option<&io::Error> {
+        self.checkpoint_error.as_ref()
+    }
+
+    /// Write the whole vector to the checkpoint file and clear the log.
+    /// The previous checkpoint is replaced atomically.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        let temporary_path = self.directory.join(TEMPORARY_CHECKPOINT_FILE);
+
+        {
+            let mut write = BufWriter::new(File::create(&temporary_path)?);
+            self.sequence.encode(&mut write)?;
+            self.elements.write_to(&mut write)?;
+            write.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+        }
+
+        fs::rename(&temporary_path, self.directory.join(CHECKPOINT_FILE))?;
+
+        // without persisting the rename, a crash could lose the new checkpoint, but keep the truncated log
+        sync_directory(&self.directory)?;
+
+        // records contained in the checkpoint are skipped when replaying, so a crash before this is harmless
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log_length = 0;
+        self.poisoned = false;
+        self.records_since_checkpoint = 0;
+        self.checkpoint_error = None;
+        Ok(())
+    }
+
+
+
+    /// Read-only access to the current state
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.elements
+    }
+
+    /// Log the insertion, then associate the element with a currently unused id
+    pub fn insert(&mut self, element: T) -> io::Result<Id<T>> {
+        let id = self.elements.next_insert_id();
+        self.log(INSERT, id, Some(&element))?;
+        self.elements.insert_at(id, element);
+        self.checkpoint_if_due();
+        Ok(id)
+    }
+
+    /// Log the removal, then remove the element.
+    /// Ignores invalid and deleted ids, returning `None` without logging.
+    pub fn remove(&mut self, id: Id<T>) -> io::Result<Option<T>> {
+        if !self.contains_id(id) {
+            return Ok(None);
+        }
+
+        self.log(REMOVE, id, None)?;
+        let removed = self.elements.remove(id);
+        self.checkpoint_if_due();
+        Ok(removed)
+    }
+
+    /// Log the new element, then replace the element with it, returning the previous element.
+    /// Ignores invalid and deleted ids, returning `None` without logging.
+    pub fn update(&mut self, id: Id<T>, element: T) -> io::Result<Option<T>> {
+        if !self.contains_id(id) {
+            return Ok(None);
+        }
+
+        self.log(UPDATE, id, Some(&element))?;
+        let previous = self.elements.insert_at(id, element);
+        self.checkpoint_if_due();
+        Ok(previous)
+    }
+}
+
+/// Read-only access to the current state, such as `get`, `iter` and indexing
+impl<T> ::std::ops::Deref for DurableIdVec<T> {
+    type Target = IdVec<T>;
+    fn deref(&self) -> &IdVec<T> {
+        &self.elements
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A fresh directory inside the temporary directory of the system
+    fn temporary_directory(name: &str) -> PathBuf {
+        let directory = ::std::env::temp_dir()
+            .join(format!("id-vec-durable-{}-{}", name, ::std::process::id()));
+
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    pub fn test_recover_from_log_and_checkpoint(){
+        let directory = temporary_directory("recover");
+
+        {
+            let mut vec = DurableIdVec::open_with_checkpoint_interval(&directory, 3).unwrap();
+            let a = vec.insert(String::from("a")).unwrap();
+            let b = vec.insert(String::from("b")).unwrap();
+            vec.update(a, String::from("x")).unwrap(); // writes a checkpoint
+            vec.remove(b).unwrap();
+            vec.insert(String::from("c")).unwrap();
+            assert_eq!(vec.update(Id::from_index(7), String::from("ignored")).unwrap(), None);
+        }
+
+        let recovered: DurableIdVec<String> = DurableIdVec::open(&directory).unwrap();
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(recovered[Id::from_index(0)], "x");
+        assert_eq!(recovered[Id::from_index(1)], "c");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    pub fn test_crash_between_checkpoint_and_truncation(){
+        let directory = temporary_directory("checkpoint-crash");
+
+        {
+            let mut vec = DurableIdVec::open_with_checkpoint_interval(&directory, usize::MAX).unwrap();
+            let a = vec.insert(String::from("a")).unwrap();
+            vec.insert(String::from("b")).unwrap();
+            vec.update(a, String::from("x")).unwrap();
+
+            let log = fs::read(directory.join(LOG_FILE)).unwrap();
+            vec.checkpoint().unwrap();
+            assert_eq!(fs::metadata(directory.join(LOG_FILE)).unwrap().len(), 0);
+
+            // the renamed checkpoint was persisted, but the log was not truncated yet,
+            // and a later checkpoint was interrupted while writing the temporary file
+            fs::write(directory.join(LOG_FILE), &log).unwrap();
+            fs::write(directory.join(TEMPORARY_CHECKPOINT_FILE), b"incomplete").unwrap();
+        }
+
+        {
+            let mut recovered: DurableIdVec<String> = DurableIdVec::open(&directory).unwrap();
+            assert_eq!(recovered.len(), 2);
+            assert_eq!(recovered[Id::from_index(0)], "x", "records of the checkpoint are not replayed twice");
+
+            recovered.remove(Id::from_index(0)).unwrap();
+            recovered.insert(String::from("c")).unwrap();
+        }
+
+        let recovered: DurableIdVec<String> = DurableIdVec::open(&directory).unwrap();
+        assert_eq!(recovered.len(), 2, "new records follow the sequence of the checkpoint");
+        assert_eq!(recovered[Id::from_index(0)], "c");
+        assert_eq!(recovered[Id::from_index(1)], "b");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    pub fn test_failed_checkpoint_keeps_modification(){
+        let directory = temporary_directory("failed-checkpoint");
+        let mut vec = DurableIdVec::open_with_checkpoint_interval(&directory, 1).unwrap();
+
+        // creating the temporary checkpoint file fails while a directory is in its place
+        fs::create_dir(directory.join(TEMPORARY_CHECKPOINT_FILE)).unwrap();
+        let a = vec.insert(String::from("a")).unwrap();
+        assert!(vec.checkpoint_error().is_some());
+        assert_eq!(vec[a], "a");
+
+        fs::remove_dir(directory.join(TEMPORARY_CHECKPOINT_FILE)).unwrap();
+        vec.insert(String::from("b")).unwrap(); // retries the checkpoint
+        assert!(vec.checkpoint_error().is_none());
+        assert_eq!(fs::metadata(directory.join(LOG_FILE)).unwrap().len(), 0);
+        drop(vec);
+
+        let recovered: DurableIdVec<String> = DurableIdVec::open(&directory).unwrap();
+        assert_eq!(recovered.len(), 2, "each insertion is applied exactly once");
+        assert_eq!(recovered[a], "a");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    pub fn test_torn_records_are_ignored(){
+        let directory = temporary_directory("torn");
+        let mut states = Vec::new();
+        let mut log_lengths = Vec::new();
+
+        {
+            let mut vec = DurableIdVec::open_with_checkpoint_interval(&directory, usize::MAX).unwrap();
+            let log_length = || fs::metadata(directory.join(LOG_FILE)).unwrap().len();
+            states.push(vec.as_id_vec().clone());
+            log_lengths.push(log_length());
+
+            let first = vec.insert(1_u32).unwrap();
+            states.push(vec.as_id_vec().clone()); log_lengths.push(log_length());
+
+            vec.insert(2).unwrap();
+            states.push(vec.as_id_vec().clone()); log_lengths.push(log_length());
+
+            vec.remove(first).unwrap();
+            states.push(vec.as_id_vec().clone()); log_lengths.push(log_length());
+
+            vec.update(Id::from_index(1), 20).unwrap();
+            states.push(vec.as_id_vec().clone()); log_lengths.push(log_length());
+        }
+
+        let log = fs::read(directory.join(LOG_FILE)).unwrap();
+        assert_eq!(log.len() as u64, *log_lengths.last().unwrap());
+
+        // simulate a crash at every possible position
+        for offset in 0 ..= log.len() {
+            fs::write(directory.join(LOG_FILE), &log[.. offset]).unwrap();
+
+            let complete_records = log_lengths.iter().filter(|&&length| length <= offset as u64).count() - 1;
+            let recovered: DurableIdVec<u32> = DurableIdVec::open(&directory).unwrap();
+            assert_eq!(recovered.as_id_vec(), &states[complete_records], "truncated at {}", offset);
+            assert_eq!(fs::metadata(directory.join(LOG_FILE)).unwrap().len(), log_lengths[complete_records]);
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
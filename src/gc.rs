@@ -0,0 +1,89 @@
+
+use ::id::*;
+use ::vec::IdVec;
+use ::bits::BitSet;
+
+
+/// Implemented by elements which store the ids of other elements inside the same `IdVec`.
+/// Enables removing all elements which cannot be reached from a set of roots.
+pub trait Trace: Sized {
+
+    /// Call `visit` for every id stored inside this element.
+    /// Ids which are not contained in the `IdVec` are ignored.
+    fn trace<F: FnMut(Id<Self>)>(&self, visit: F);
+}
+
+
+impl<T> IdVec<T> where T: Trace {
+
+    /// Mark all elements reachable from the roots, and remove all other elements.
+    /// Unlike reference counting, this also removes unreachable cycles.
+    /// Returns the ids of all removed elements.
+    /// Make sure that no ids pointing to removed elements exist outside of this IdVec after this call.
+    pub fn collect_garbage<R>(&mut self, roots: R) -> Vec<Id<T>> where R: IntoIterator<Item = Id<T>> {
+        let mut reachable = BitSet::new(self.id_index_limit());
+        let mut stack: Vec<Id<T>> = roots.into_iter().collect();
+
+        while let Some(id) = stack.pop() {
+            if self.contains_id(id) && reachable.insert(id.index_value()) {
+                self[id].trace(|referenced| stack.push(referenced));
+            }
+        }
+
+        let garbage: Vec<Id<T>> = self.ids()
+            .filter(|id| !reachable.contains(id.index_value()))
+            .collect();
+
+        for &id in &garbage {
+            self.remove(id);
+        }
+
+        garbage
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Node {
+        references: Vec<Id<Node>>,
+    }
+
+    impl Trace for Node {
+        fn trace<F: FnMut(Id<Self>)>(&self, visit: F) {
+            self.references.iter().cloned().for_each(visit)
+        }
+    }
+
+    fn node(references: &[Index]) -> Node {
+        Node { references: references.iter().map(|&index| Id::from_index(index)).collect() }
+    }
+
+    #[test]
+    pub fn test_collect_unreachable(){
+        // 0 -> 1 -> 2, 3 -> 2, 4 -> 5 -> 4
+        let mut nodes = id_vec!(node(&[1]), node(&[2]), node(&[]), node(&[2]), node(&[5]), node(&[4]));
+
+        let garbage = nodes.collect_garbage(vec![ Id::from_index(0) ]);
+        assert_eq!(
+            garbage.iter().map(|id| id.index_value()).collect::<Vec<_>>(),
+            vec![3, 4, 5], "unreachable elements and cycles removed"
+        );
+
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes.contains_id(Id::from_index(2)));
+    }
+
+    #[test]
+    pub fn test_collect_reachable_cycle(){
+        let mut nodes = id_vec!(node(&[1]), node(&[0]), node(&[7]));
+        assert_eq!(nodes.collect_garbage(vec![ Id::from_index(1) ]), vec![ Id::from_index(2) ]);
+        assert!(nodes.is_packed());
+
+        assert_eq!(nodes.collect_garbage(vec![]).len(), 2, "no roots remove everything");
+        assert!(nodes.is_empty());
+    }
+}
@@ -0,0 +1,430 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Used as a key to access a node inside a `Graph<N, E>`
+pub type NodeId<N> = Id<Node<N>>;
+
+/// Used as a key to access an edge inside a `Graph<N, E>`
+pub type EdgeId<E> = Id<Edge<E>>;
+
+
+/// Whether the edges of a graph have a direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GraphKind {
+    Directed, Undirected
+}
+
+/// Selects which of the edges connected to a node should be visited.
+/// In an undirected graph, both directions yield all connected edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Outgoing, Incoming
+}
+
+
+/// A node weight, together with the indices of all connected edges.
+#[derive(Clone, Debug)]
+pub struct Node<N> {
+    weight: N,
+
+    /// Indices of edges starting at this node
+    outgoing: Vec<Index>,
+
+    /// Indices of edges ending at this node
+    incoming: Vec<Index>,
+}
+
+/// An edge weight, together with the indices of both connected nodes.
+#[derive(Clone, Debug)]
+pub struct Edge<E> {
+    weight: E,
+    source: Index,
+    target: Index,
+}
+
+
+/// Stores nodes and edges in two separate `IdVec`s.
+/// Every node knows its incoming and outgoing edges,
+/// so that removing a node can also remove all connected edges.
+#[derive(Clone, Debug)]
+pub struct Graph<N, E> {
+    nodes: IdVec<Node<N>>,
+    edges: IdVec<Edge<E>>,
+    kind: GraphKind,
+}
+
+
+impl<N, E> Graph<N, E> {
+
+    /// Does not allocate heap memory
+    pub fn new(kind: GraphKind) -> Self {
+        Graph { nodes: IdVec::new(), edges: IdVec::new(), kind }
+    }
+
+    /// Does not allocate heap memory
+    pub fn directed() -> Self {
+        Self::new(GraphKind::Directed)
+    }
+
+    /// Does not allocate heap memory
+    pub fn undirected() -> Self {
+        Self::new(GraphKind::Undirected)
+    }
+
+    pub fn kind(&self) -> GraphKind {
+        self.kind
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.kind == GraphKind::Directed
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn contains_node(&self, node: NodeId<N>) -> bool {
+        self.nodes.contains_id(node)
+    }
+
+    pub fn contains_edge(&self, edge: EdgeId<E>) -> bool {
+        self.edges.contains_id(edge)
+    }
+
+
+
+    /// Insert a new node without any edges
+    pub fn add_node(&mut self, weight: N) -> NodeId<N> {
+        self.nodes.insert(Node { weight, outgoing: Vec::new(), incoming: Vec::new() })
+    }
+
+    /// Connect two nodes with a new edge.
+    /// In an undirected graph, the order of source and target does not matter.
+    /// Panics on invalid node ids.
+    pub fn add_edge(&mut self, source: NodeId<N>, target: NodeId<N>, weight: E) -> EdgeId<E> {
+        assert!(self.contains_node(source), "Adding edge from invalid node `{:?}`", source);
+        assert!(self.contains_node(target), "Adding edge to invalid node `{:?}`", target);
+
+        let edge = self.edges.insert(Edge {
+            weight,
+            source: source.index_value(),
+            target: target.index_value(),
+        });
+
+        self.nodes[source].outgoing.push(edge.index_value());
+        self.nodes[target].incoming.push(edge.index_value());
+        edge
+    }
+
+    /// Remove the edge and disconnect it from both nodes.
+    /// Ignores invalid and deleted ids.
+    pub fn remove_edge(&mut self, edge: EdgeId<E>) {
+        if let Some(&Edge { source, target, .. }) = self.edges.get(edge) {
+            let index = edge.index_value();
+            self.nodes[Id::from_index(source)].outgoing.retain(|&e| e != index);
+            self.nodes[Id::from_index(target)].incoming.retain(|&e| e != index);
+            self.edges.remove(edge);
+        }
+    }
+
+    /// Remove the node and all edges connected to it.
+    /// Make sure that no ids pointing to that node or its edges exist after this call.
+    /// Ignores invalid and deleted ids.
+    pub fn remove_node(&mut self, node: NodeId<N>) {
+        if let Some(removed) = self.nodes.get(node) {
+            let connected: Vec<Index> = removed.outgoing.iter()
+                .chain(removed.incoming.iter()).cloned().collect();
+
+            for edge in connected {
+                self.remove_edge(Id::from_index(edge)); // ignores self-loops which have already been removed
+            }
+
+            self.nodes.remove(node);
+        }
+    }
+
+    /// Removes all nodes and edges, instantly deallocating
+    pub fn clear(&mut self){
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+
+
+    /// Return a reference to the weight of the node that this id points to
+    pub fn node(&self, node: NodeId<N>) -> Option<&N> {
+        self.nodes.get(node).map(|node| &node.weight)
+    }
+
+    /// Return a mutable reference to the weight of the node that this id points to
+    pub fn node_mut(&mut self, node: NodeId<N>) -> Option<&mut N> {
+        self.nodes.get_mut(node).map(|node| &mut node.weight)
+    }
+
+    /// Return a reference to the weight of the edge that this id points to
+    pub fn edge(&self, edge: EdgeId<E>) -> Option<&E> {
+        self.edges.get(edge).map(|edge| &edge.weight)
+    }
+
+    /// Return a mutable reference to the weight of the edge that this id points to
+    pub fn edge_mut(&mut self, edge: EdgeId<E>) -> Option<&mut E> {
+        self.edges.get_mut(edge).map(|edge| &mut edge.weight)
+    }
+
+    /// Returns the source and target node of the edge
+    pub fn edge_endpoints(&self, edge: EdgeId<E>) -> Option<(NodeId<N>, NodeId<N>)> {
+        self.edges.get(edge).map(|edge| {
+            (Id::from_index(edge.source), Id::from_index(edge.target))
+        })
+    }
+
+    /// Find any edge from `source` to `target`.
+    /// In an undirected graph, also finds edges from `target` to `source`.
+    pub fn find_edge(&self, source: NodeId<N>, target: NodeId<N>) -> Option<EdgeId<E>> {
+        self.edges_directed(source, Direction::Outgoing)
+            .find(|&(edge, _)| self.edge_endpoints(edge).is_some_and(|(from, to)| {
+                (from == source && to == target) || (!self.is_directed() && from == target && to == source)
+            }))
+            .map(|(edge, _)| edge)
+    }
+
+
+
+    /// Used for immutable access to node ids and node weights
+    pub fn nodes<'s>(&'s self) -> impl DoubleEndedIterator<Item = (NodeId<N>, &'s N)> + 's {
+        self.nodes.iter().map(|(id, node)| (id, &node.weight))
+    }
+
+    /// Used for immutable access to edge ids and edge weights
+    pub fn edges<'s>(&'s self) -> impl DoubleEndedIterator<Item = (EdgeId<E>, &'s E)> + 's {
+        self.edges.iter().map(|(id, edge)| (id, &edge.weight))
+    }
+
+    /// All nodes that can be reached by following a single edge.
+    /// In a directed graph, these are the targets of the outgoing edges.
+    /// In an undirected graph, these are all nodes connected to this node.
+    /// Panics on invalid node ids.
+    pub fn neighbors(&self, node: NodeId<N>) -> NeighborIter<'_, N, E> {
+        self.neighbors_directed(node, Direction::Outgoing)
+    }
+
+    /// All nodes connected to this node by an edge in the specified direction.
+    /// In an undirected graph, the direction is ignored.
+    /// Panics on invalid node ids.
+    pub fn neighbors_directed(&self, node: NodeId<N>, direction: Direction) -> NeighborIter<'_, N, E> {
+        NeighborIter { node: node.index_value(), edges: self.edges_directed(node, direction) }
+    }
+
+    /// All edges connected to this node in the specified direction.
+    /// In an undirected graph, the direction is ignored, and all connected edges are returned.
+    /// Panics on invalid node ids.
+    pub fn edges_directed(&self, node: NodeId<N>, direction: Direction) -> EdgeIter<'_, N, E> {
+        let node_index = node.index_value();
+        let node = &self.nodes[node];
+
+        let (primary, secondary) = match (self.kind, direction) {
+            (GraphKind::Directed, Direction::Outgoing) => (&node.outgoing, &[][..]),
+            (GraphKind::Directed, Direction::Incoming) => (&node.incoming, &[][..]),
+            (GraphKind::Undirected, _) => (&node.outgoing, &node.incoming[..]),
+        };
+
+        EdgeIter {
+            node: node_index,
+            primary: primary.iter(),
+            secondary: secondary.iter(),
+            edges: &self.edges,
+            marker: ::std::marker::PhantomData,
+        }
+    }
+}
+
+
+impl<N, E> ::std::ops::Index<NodeId<N>> for Graph<N, E> {
+    type Output = N;
+    fn index(&self, node: NodeId<N>) -> &N {
+        &self.nodes[node].weight
+    }
+}
+
+impl<N, E> ::std::ops::IndexMut<NodeId<N>> for Graph<N, E> {
+    fn index_mut(&mut self, node: NodeId<N>) -> &mut N {
+        &mut self.nodes[node].weight
+    }
+}
+
+impl<N, E> ::std::ops::Index<EdgeId<E>> for Graph<N, E> {
+    type Output = E;
+    fn index(&self, edge: EdgeId<E>) -> &E {
+        &self.edges[edge].weight
+    }
+}
+
+impl<N, E> ::std::ops::IndexMut<EdgeId<E>> for Graph<N, E> {
+    fn index_mut(&mut self, edge: EdgeId<E>) -> &mut E {
+        &mut self.edges[edge].weight
+    }
+}
+
+
+
+/// Iterates over the edges connected to a single node.
+/// Self-loops are yielded only once.
+pub struct EdgeIter<'s, N: 's, E: 's> {
+    node: Index,
+    primary: ::std::slice::Iter<'s, Index>,
+
+    /// The incoming edges of an undirected graph, empty in a directed graph
+    secondary: ::std::slice::Iter<'s, Index>,
+
+    edges: &'s IdVec<Edge<E>>,
+    marker: ::std::marker::PhantomData<N>,
+}
+
+impl<'s, N: 's, E: 's> Iterator for EdgeIter<'s, N, E> {
+    type Item = (EdgeId<E>, &'s E);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let edges = self.edges;
+        let node = self.node;
+
+        let index = self.primary.next().cloned().or_else(|| {
+            // skip self-loops, as they have already been visited in the primary list
+            self.secondary.by_ref().cloned().find(|&edge| edges[Id::from_index(edge)].source != node)
+        });
+
+        index.map(|index| {
+            let id = Id::from_index(index);
+            (id, &edges[id].weight)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let primary = self.primary.len();
+        (primary, Some(primary + self.secondary.len()))
+    }
+}
+
+
+/// Iterates over the nodes connected to a single node.
+pub struct NeighborIter<'s, N: 's, E: 's> {
+    node: Index,
+    edges: EdgeIter<'s, N, E>,
+}
+
+impl<'s, N: 's, E: 's> Iterator for NeighborIter<'s, N, E> {
+    type Item = NodeId<N>;
+
+    fn next(&mut self) -> Option<NodeId<N>> {
+        let node = self.node;
+
+        self.edges.next().map(|(id, _)| {
+            let edge = &self.edges.edges[id];
+            Id::from_index(if edge.source == node { edge.target } else { edge.source })
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.edges.size_hint()
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_add_and_remove_edges(){
+        let mut graph = Graph::directed();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+
+        let ab = graph.add_edge(a, b, 1);
+        let ac = graph.add_edge(a, c, 2);
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.edge_endpoints(ab), Some((a, b)));
+        assert_eq!(graph[ac], 2);
+        assert_eq!(graph.find_edge(a, c), Some(ac));
+        assert_eq!(graph.find_edge(c, a), None, "directed edges have one direction");
+
+        graph.remove_edge(ab);
+        assert!(!graph.contains_edge(ab));
+        assert_eq!(graph.neighbors(a).collect::<Vec<_>>(), vec![c]);
+        assert_eq!(graph.neighbors_directed(b, Direction::Incoming).count(), 0);
+    }
+
+    #[test]
+    pub fn test_directed_neighbors(){
+        let mut graph = Graph::directed();
+        let a = graph.add_node(0);
+        let b = graph.add_node(1);
+        let c = graph.add_node(2);
+
+        graph.add_edge(a, b, ());
+        graph.add_edge(c, a, ());
+        graph.add_edge(a, a, ());
+
+        assert_eq!(graph.neighbors(a).collect::<Vec<_>>(), vec![b, a]);
+        assert_eq!(graph.neighbors_directed(a, Direction::Incoming).collect::<Vec<_>>(), vec![c, a]);
+        assert_eq!(graph.edges_directed(b, Direction::Outgoing).count(), 0);
+        assert_eq!(graph.edges_directed(b, Direction::Incoming).count(), 1);
+    }
+
+    #[test]
+    pub fn test_undirected_neighbors(){
+        let mut graph = Graph::undirected();
+        let a = graph.add_node(0);
+        let b = graph.add_node(1);
+        let c = graph.add_node(2);
+
+        let ab = graph.add_edge(a, b, "ab");
+        let ca = graph.add_edge(c, a, "ca");
+        let aa = graph.add_edge(a, a, "aa");
+
+        assert_eq!(graph.neighbors(a).collect::<Vec<_>>(), vec![b, a, c], "self-loops yielded once");
+        assert_eq!(graph.neighbors(b).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(
+            graph.edges_directed(a, Direction::Incoming).map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![ab, aa, ca],
+            "undirected edges ignore direction"
+        );
+
+        assert_eq!(graph.find_edge(a, c), Some(ca));
+    }
+
+    #[test]
+    pub fn test_remove_node(){
+        let mut graph = Graph::directed();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+
+        let ab = graph.add_edge(a, b, ());
+        let bc = graph.add_edge(b, c, ());
+        let ca = graph.add_edge(c, a, ());
+        let bb = graph.add_edge(b, b, ());
+
+        graph.remove_node(b);
+        assert!(!graph.contains_node(b));
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+
+        assert!(!graph.contains_edge(ab));
+        assert!(!graph.contains_edge(bc));
+        assert!(!graph.contains_edge(bb));
+        assert!(graph.contains_edge(ca));
+
+        assert_eq!(graph.neighbors(a).count(), 0);
+        assert_eq!(graph.neighbors(c).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(graph.neighbors_directed(c, Direction::Incoming).count(), 0);
+    }
+}
@@ -18,26 +18,26 @@ impl<T> Id<T> {
     /// Convenience function which allows writing the index first, and the IdVec afterwards.
     /// Example: `the_selected_entity.of(entities)`
     /// Panics when calling on an invalid id
-    pub fn of<'s>(self, vec: &'s IdVec<T>) -> &'s T {
+    pub fn of(self, vec: &IdVec<T>) -> &T {
         &vec[self]
     }
 
     /// Convenience function which allows writing the index first, and the IdVec afterwards.
     /// Example: `the_selected_entity.of_mut(entities)`
     /// Panics when calling on an invalid id
-    pub fn of_mut<'s>(self, vec: &'s mut IdVec<T>) -> &'s mut T {
+    pub fn of_mut(self, vec: &mut IdVec<T>) -> &mut T {
         &mut vec[self]
     }
 
     /// Convenience function which allows writing the index first, and the IdVec afterwards.
     /// Example: `the_selected_entity.try_of(entities)`
-    pub fn try_of<'s>(self, vec: &'s IdVec<T>) -> Option<&'s T> {
+    pub fn try_of(self, vec: &IdVec<T>) -> Option<&T> {
         vec.get(self)
     }
 
     /// Convenience function which allows writing the index first, and the IdVec afterwards.
     /// Example: `the_selected_entity.try_of_mut(entities)`
-    pub fn try_of_mut<'s>(self, vec: &'s mut IdVec<T>) -> Option<&'s mut T> {
+    pub fn try_of_mut(self, vec: &mut IdVec<T>) -> Option<&mut T> {
         vec.get_mut(self)
     }
 
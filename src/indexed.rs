@@ -0,0 +1,326 @@
+
+use ::std::borrow::Borrow;
+use ::std::collections::HashMap;
+use ::std::hash::Hash;
+use ::std::ops::Deref;
+use ::id::*;
+use ::vec::IdVec;
+use ::keyed::{ KeyedIdVec, KeyIndex, KeyGuard };
+
+
+/// An `IdVec` which maintains a hash index over a key derived from each element,
+/// enabling lookups by key, and by element, in constant average time.
+/// Optionally, the index rejects elements whose key is already used.
+/// Read access to the elements is provided by dereferencing to the `IdVec`.
+pub struct IndexedIdVec<T, K> {
+    /// The index contains the ids of all elements with a key, in insertion order,
+    /// and exactly one id per key if the keys are unique
+    keyed: KeyedIdVec<T, K, HashMap<K, Vec<Id<T>>>>,
+
+    unique: bool,
+}
+
+
+/// Returned when inserting an element into a vector with unique keys,
+/// if another element with the same key already exists
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateKey<T> {
+    /// The element with the same key, which is already contained in the vector
+    pub existing: Id<T>,
+
+    /// The rejected element
+    pub element: T,
+}
+
+
+impl<T, K> IndexedIdVec<T, K> where K: Hash + Eq {
+
+    /// Create a vector which allows multiple elements with the same key.
+    /// Example: `IndexedIdVec::new(|user: &User| user.name.clone())`
+    pub fn new(key: fn(&T) -> K) -> Self {
+        Self::from_id_vec(IdVec::new(), key)
+    }
+
+    /// Create a vector which rejects elements whose key is already used
+    pub fn with_unique_keys(key: fn(&T) -> K) -> Self {
+        IndexedIdVec { unique: true, .. Self::new(key) }
+    }
+
+    /// Index all elements of an existing IdVec, allowing multiple elements with the same key
+    pub fn from_id_vec(elements: IdVec<T>, key: fn(&T) -> K) -> Self {
+        IndexedIdVec { keyed: KeyedIdVec::from_id_vec(elements, HashMap::new(), key), unique: false }
+    }
+
+    /// Index all elements of an existing IdVec, rejecting elements whose key is already used.
+    /// Fails with the first element, in the order of ids, whose key is used by an element with a smaller id.
+    pub fn from_id_vec_with_unique_keys(elements: IdVec<T>, key: fn(&T) -> K) -> Result<Self, DuplicateKey<T>> {
+        let mut vec = IndexedIdVec { unique: true, .. Self::from_id_vec(elements, key) };
+
+        let first_duplicate = vec.keyed.index.values()
+            .filter(|ids| ids.len() > 1)
+            .map(|ids| (ids[0], ids[1]))
+            .min_by_key(|&(_, duplicate)| duplicate.index_value());
+
+        match first_duplicate {
+            Some((existing, duplicate)) => Err(DuplicateKey {
+                existing, element: vec.keyed.elements.remove(duplicate).expect("indexed element not found")
+            }),
+
+            None => Ok(vec),
+        }
+    }
+
+    /// Read-only access to the current state
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.keyed.elements
+    }
+
+    /// Drop the index
+    pub fn into_id_vec(self) -> IdVec<T> {
+        self.keyed.elements
+    }
+
+    /// Whether inserting fails for keys which are already used
+    pub fn has_unique_keys(&self) -> bool {
+        self.unique
+    }
+
+    fn existing(&self, key: &K) -> Option<Id<T>> {
+        self.keyed.index.get(key).and_then(|ids| ids.first()).cloned()
+    }
+
+
+
+    /// Return a guard which allows modifying the element that this id points to.
+    /// The element is re-indexed when the guard is dropped.
+    /// Returns `None` if the keys are unique, as the modified key could already be used, use `modify` instead.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<KeyGuard<'_, T, K>> {
+        if self.unique { None }
+        else { self.keyed.get_mut(id) }
+    }
+
+    /// Associate the specified element with a currently unused id.
+    /// Fails without inserting if the keys are unique and the key of the element is already used.
+    pub fn insert(&mut self, element: T) -> Result<Id<T>, DuplicateKey<T>> {
+        if self.unique {
+            if let Some(existing) = self.existing(&(self.keyed.key)(&element)) {
+                return Err(DuplicateKey { existing, element });
+            }
+        }
+
+        Ok(self.keyed.insert(element))
+    }
+
+    /// Replace the element that this id points to, returning the previous element.
+    /// Fails without replacing if the keys are unique and the new key is used by another element.
+    /// Panics on invalid id.
+    pub fn replace(&mut self, id: Id<T>, element: T) -> Result<T, DuplicateKey<T>> {
+        let key = (self.keyed.key)(&element);
+        let previous_key = (self.keyed.key)(&self.keyed.elements[id]);
+
+        if key != previous_key {
+            if let Some(existing) = self.existing(&key).filter(|_| self.unique) {
+                return Err(DuplicateKey { existing, element });
+            }
+
+            KeyIndex::remove(&mut self.keyed.index, &previous_key, id);
+            KeyIndex::add(&mut self.keyed.index, key, id);
+        }
+
+        Ok(::std::mem::replace(&mut self.keyed.elements[id], element))
+    }
+
+    /// Modify a copy of the element that this id points to, and replace the element with it.
+    /// Fails without modifying if the keys are unique and the modified key is used by another element.
+    /// Panics on invalid id.
+    pub fn modify<F>(&mut self, id: Id<T>, modify: F) -> Result<(), DuplicateKey<T>> where F: FnOnce(&mut T), T: Clone {
+        let mut element = self.keyed.elements[id].clone();
+        modify(&mut element);
+        self.replace(id, element).map(|_| ())
+    }
+
+    /// Remove the element and its key from the index.
+    /// Ignores invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        self.keyed.remove(id)
+    }
+
+    /// Remove all elements and keys, keeping the allocated memory
+    pub fn clear(&mut self) {
+        self.keyed.clear();
+    }
+
+
+
+    /// Any element with this key, the first inserted one if there are multiple.
+    /// Average complexity of O(1).
+    pub fn find_by_key<Q>(&self, key: &Q) -> Option<Id<T>> where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.find_all_by_key(key).first().cloned()
+    }
+
+    /// All elements with this key, in insertion order
+    pub fn find_all_by_key<Q>(&self, key: &Q) -> &[Id<T>] where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.keyed.index.get(key).map_or(&[], |ids| ids.as_slice())
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.keyed.index.contains_key(key)
+    }
+
+    /// Only compares the elements with the same key as the searched element.
+    /// Average complexity of O(1) if keys are rarely shared.
+    pub fn find_id_of_element(&self, element: &T) -> Option<Id<T>> where T: PartialEq {
+        self.find_all_by_key(&(self.keyed.key)(element)).iter()
+            .find(|&&id| self[id] == *element)
+            .cloned()
+    }
+
+    /// Average complexity of O(1) if keys are rarely shared
+    pub fn contains_element(&self, element: &T) -> bool where T: PartialEq {
+        self.find_id_of_element(element).is_some()
+    }
+
+    /// Whether both vectors contain equal elements the same number of times, ignoring the ids.
+    /// Average complexity of O(n).
+    pub fn elements_eq(&self, other: &Self) -> bool where T: Hash + Eq {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        let mut counts: HashMap<&T, usize> = HashMap::with_capacity(self.len());
+        for element in self.elements() {
+            *counts.entry(element).or_insert(0) += 1;
+        }
+
+        for element in other.elements() {
+            match counts.get_mut(element) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => return false,
+            }
+        }
+
+        true
+    }
+}
+
+impl<T, K> Deref for IndexedIdVec<T, K> {
+    type Target = IdVec<T>;
+    fn deref(&self) -> &IdVec<T> {
+        &self.keyed.elements
+    }
+}
+
+/// The ids of all elements with a key, in insertion order
+impl<T, K> KeyIndex<T, K> for HashMap<K, Vec<Id<T>>> where K: Hash + Eq {
+    fn add(&mut self, key: K, id: Id<T>) {
+        self.entry(key).or_default().push(id);
+    }
+
+    fn remove(&mut self, key: &K, id: Id<T>) {
+        let now_empty = self.get_mut(key).is_some_and(|ids| {
+            ids.retain(|&indexed| indexed != id);
+            ids.is_empty()
+        });
+
+        if now_empty {
+            HashMap::remove(self, key);
+        }
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self);
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct User { name: String, age: u32 }
+
+    fn user(name: &str, age: u32) -> User {
+        User { name: name.to_owned(), age }
+    }
+
+    fn name(user: &User) -> String {
+        user.name.clone()
+    }
+
+    #[test]
+    pub fn test_unique_keys(){
+        let mut users = IndexedIdVec::with_unique_keys(name);
+        let alice = users.insert(user("alice", 30)).unwrap();
+        let bob = users.insert(user("bob", 40)).unwrap();
+
+        let duplicate = users.insert(user("alice", 50));
+        assert_eq!(duplicate, Err(DuplicateKey { existing: alice, element: user("alice", 50) }));
+        assert_eq!(users.len(), 2);
+
+        assert_eq!(users.find_by_key("alice"), Some(alice));
+        assert_eq!(users.replace(bob, user("alice", 1)).map_err(|error| error.existing), Err(alice));
+
+        users.modify(alice, |alice| alice.name = String::from("carol")).unwrap();
+        assert_eq!(users.find_by_key("alice"), None);
+        assert_eq!(users.find_by_key("carol"), Some(alice));
+
+        users.modify(bob, |bob| bob.age = 41).unwrap();
+        assert_eq!(users.find_by_key("bob"), Some(bob));
+        assert_eq!(users.find_id_of_element(&user("bob", 41)), Some(bob));
+        assert!(!users.contains_element(&user("bob", 40)));
+
+        assert_eq!(users.remove(bob), Some(user("bob", 41)));
+        assert!(!users.contains_key("bob"));
+        assert!(users.insert(user("bob", 1)).is_ok(), "key is free after removal");
+    }
+
+    #[test]
+    pub fn test_modify_rejects_duplicate_key(){
+        let mut users = IndexedIdVec::with_unique_keys(name);
+        let alice = users.insert(user("alice", 30)).unwrap();
+        let bob = users.insert(user("bob", 40)).unwrap();
+
+        let result = users.modify(alice, |alice| { alice.name = String::from("bob"); alice.age = 31; });
+        assert_eq!(result, Err(DuplicateKey { existing: bob, element: user("bob", 31) }));
+
+        assert_eq!(users[alice], user("alice", 30), "element is not modified");
+        assert_eq!(users.find_by_key("alice"), Some(alice));
+        assert_eq!(users.find_all_by_key("bob"), &[bob]);
+
+        assert!(users.get_mut(alice).is_none(), "guards are not available for unique keys");
+    }
+
+    #[test]
+    pub fn test_from_id_vec_with_unique_keys(){
+        let unique = id_vec!(user("alice", 30), user("bob", 40));
+        let users = IndexedIdVec::from_id_vec_with_unique_keys(unique, name).unwrap();
+        assert!(users.has_unique_keys());
+        assert_eq!(users.find_by_key("bob"), Some(Id::from_index(1)));
+
+        let duplicates = id_vec!(user("alice", 30), user("bob", 40), user("bob", 41), user("alice", 31));
+        let error = IndexedIdVec::from_id_vec_with_unique_keys(duplicates, name).err();
+        assert_eq!(error, Some(DuplicateKey { existing: Id::from_index(1), element: user("bob", 41) }));
+    }
+
+    #[test]
+    pub fn test_shared_keys_and_elements_eq(){
+        let mut by_age = IndexedIdVec::new(|user: &User| user.age);
+        let alice = by_age.insert(user("alice", 30)).unwrap();
+        let bob = by_age.insert(user("bob", 30)).unwrap();
+        by_age.insert(user("bob", 30)).unwrap();
+
+        assert_eq!(by_age.find_all_by_key(&30).len(), 3);
+        assert_eq!(by_age.find_by_key(&30), Some(alice));
+        assert_eq!(by_age.find_id_of_element(&user("bob", 30)), Some(bob));
+
+        let shuffled = id_vec!(user("bob", 30), user("alice", 30), user("bob", 30));
+        let mut other = IndexedIdVec::from_id_vec(shuffled, |user: &User| user.age);
+        assert!(by_age.elements_eq(&other));
+
+        other.remove(Id::from_index(0));
+        other.insert(user("alice", 30)).unwrap();
+        assert!(!by_age.elements_eq(&other), "same elements, different counts");
+    }
+}
@@ -0,0 +1,399 @@
+
+use ::std::collections::VecDeque;
+use ::std::ops::{ Deref, DerefMut };
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// A reversible modification of an `IdVec`.
+/// Applying a change returns the change which reverts it.
+pub(crate) enum Change<T> {
+    /// Insert the element at exactly this id
+    Insert(Id<T>, T),
+
+    /// Remove the element with this id
+    Remove(Id<T>),
+
+    /// Replace the element with this id
+    Replace(Id<T>, T),
+
+    /// Swap the two elements
+    Swap(Id<T>, Id<T>),
+}
+
+impl<T> Change<T> {
+
+    /// Modify the vector, returning the inverse change
+    pub(crate) fn apply(self, vec: &mut IdVec<T>) -> Change<T> {
+        match self {
+            Change::Insert(id, element) => {
+                let previous = vec.insert_at(id, element);
+                debug_assert!(previous.is_none(), "Journal inserted into used slot {:?}", id);
+                Change::Remove(id)
+            },
+
+            Change::Remove(id) => {
+                let element = vec.remove(id).expect("Journal removed unused id");
+                Change::Insert(id, element)
+            },
+
+            Change::Replace(id, element) => {
+                let previous = ::std::mem::replace(&mut vec[id], element);
+                Change::Replace(id, previous)
+            },
+
+            Change::Swap(id1, id2) => {
+                vec.swap_elements(id1, id2);
+                Change::Swap(id1, id2)
+            },
+        }
+    }
+}
+
+
+
+/// An `IdVec` which records all modifications, so that they can be undone and redone.
+/// Undoing and redoing restores the exact ids of all elements.
+pub struct JournaledIdVec<T> {
+    elements: IdVec<T>,
+
+    /// The inverse of each applied modification, newest at the back
+    undo: VecDeque<Change<T>>,
+
+    /// The inverse of each undone modification, newest at the back
+    redo: Vec<Change<T>>,
+
+    /// The maximum number of undoable modifications
+    history_limit: usize,
+
+    /// The number of modifications that have been discarded because of the history limit,
+    /// used to keep the checkpoint positions stable
+    discarded: usize,
+
+    /// Named positions in the history, as the number of modifications since creation
+    checkpoints: Vec<(String, usize)>,
+}
+
+
+impl<T> JournaledIdVec<T> {
+
+    /// Does not allocate heap memory. The history is unbounded.
+    pub fn new() -> Self {
+        Self::with_history_limit(usize::MAX)
+    }
+
+    /// Only remembers the specified number of modifications, forgetting the oldest ones
+    pub fn with_history_limit(history_limit: usize) -> Self {
+        Self::from_id_vec(IdVec::new(), history_limit)
+    }
+
+    /// Start recording modifications of an existing IdVec
+    pub fn from_id_vec(elements: IdVec<T>, history_limit: usize) -> Self {
+        JournaledIdVec {
+            elements, history_limit,
+            undo: VecDeque::new(), redo: Vec::new(),
+            discarded: 0, checkpoints: Vec::new(),
+        }
+    }
+
+    /// Read-only access to the current state
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.elements
+    }
+
+    /// Stop recording, discarding the history
+    pub fn into_id_vec(self) -> IdVec<T> {
+        self.elements
+    }
+
+    /// The current position in the history, counting all modifications since creation
+    fn position(&self) -> usize {
+        self.discarded + self.undo.len()
+    }
+
+    fn record(&mut self, inverse: Change<T>) {
+        // a new modification makes all undone modifications unreachable
+        self.redo.clear();
+        let position = self.position();
+        self.checkpoints.retain(|&(_, checkpoint)| checkpoint <= position);
+
+        self.undo.push_back(inverse);
+
+        while self.undo.len() > self.history_limit {
+            self.undo.pop_front();
+            self.discarded += 1;
+        }
+
+        let oldest = self.discarded;
+        self.checkpoints.retain(|&(_, checkpoint)| checkpoint >= oldest);
+    }
+
+
+
+    /// Associate the specified element with a currently unused id
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        let id = self.elements.insert(element);
+        self.record(Change::Remove(id));
+        id
+    }
+
+    /// Remove the element, keeping it in the history.
+    /// Ignores invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) {
+        if let Some(element) = self.elements.remove(id) {
+            self.record(Change::Insert(id, element));
+        }
+    }
+
+    /// Swap the elements pointed to. Panic on invalid Id parameter.
+    pub fn swap_elements(&mut self, id1: Id<T>, id2: Id<T>) {
+        self.elements.swap_elements(id1, id2);
+        self.record(Change::Swap(id1, id2));
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        self.elements.get(id)
+    }
+
+    /// Return a guard which allows modifying the element that this id points to.
+    /// The element is copied when it is first accessed mutably through the guard,
+    /// and the copy is recorded in the history when the guard is dropped.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<JournalGuard<'_, T>> where T: Clone {
+        if self.elements.contains_id(id) { Some(JournalGuard { journal: self, id, previous: None }) }
+        else { None }
+    }
+
+
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Revert the newest modification. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop_back() {
+            Some(change) => {
+                let inverse = change.apply(&mut self.elements);
+                self.redo.push(inverse);
+                true
+            },
+
+            None => false,
+        }
+    }
+
+    /// Repeat the newest undone modification. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(change) => {
+                let inverse = change.apply(&mut self.elements);
+                self.undo.push_back(inverse); // cannot exceed the limit, as it has been undone before
+                true
+            },
+
+            None => false,
+        }
+    }
+
+    /// Remember the current state under this name, replacing any previous checkpoint with the same name
+    pub fn checkpoint(&mut self, name: &str) {
+        let position = self.position();
+        self.checkpoints.retain(|(checkpoint, _)| checkpoint != name);
+        self.checkpoints.push((name.to_owned(), position));
+    }
+
+    /// Undo or redo all modifications until the state of the checkpoint is reached.
+    /// Returns false if the checkpoint does not exist, or has been discarded
+    /// because of the history limit or because a new modification has been made after undoing it.
+    pub fn restore_checkpoint(&mut self, name: &str) -> bool {
+        let target = match self.checkpoints.iter().find(|(checkpoint, _)| checkpoint == name) {
+            Some(&(_, position)) => position,
+            None => return false,
+        };
+
+        while self.position() > target && self.undo() {}
+        while self.position() < target && self.redo() {}
+        true
+    }
+
+    /// Forget all modifications and checkpoints, keeping the current state
+    pub fn clear_history(&mut self) {
+        self.discarded += self.undo.len();
+        self.undo.clear();
+        self.redo.clear();
+        self.checkpoints.clear();
+    }
+}
+
+impl<T> Default for JournaledIdVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ::std::ops::Index<Id<T>> for JournaledIdVec<T> {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        &self.elements[id]
+    }
+}
+
+
+
+/// Allows modifying an element of a `JournaledIdVec`.
+/// Records the previous element in the history when dropped, if the element has been accessed mutably.
+pub struct JournalGuard<'s, T: 's> {
+    journal: &'s mut JournaledIdVec<T>,
+    id: Id<T>,
+
+    /// A copy of the element, taken on the first mutable access
+    previous: Option<T>,
+}
+
+impl<'s, T: 's> Deref for JournalGuard<'s, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.journal.elements[self.id]
+    }
+}
+
+impl<'s, T: 's + Clone> DerefMut for JournalGuard<'s, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        if self.previous.is_none() {
+            self.previous = Some(self.journal.elements[self.id].clone());
+        }
+
+        &mut self.journal.elements[self.id]
+    }
+}
+
+impl<'s, T: 's> Drop for JournalGuard<'s, T> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.journal.record(Change::Replace(self.id, previous));
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn contents(journal: &JournaledIdVec<&'static str>) -> Vec<(Index, &'static str)> {
+        journal.as_id_vec().iter().map(|(id, &element)| (id.index_value(), element)).collect()
+    }
+
+    #[test]
+    pub fn test_undo_and_redo(){
+        let mut journal = JournaledIdVec::new();
+        let a = journal.insert("a");
+        let b = journal.insert("b");
+        let c = journal.insert("c");
+        journal.remove(b);
+        journal.remove(c);
+        journal.swap_elements(a, a);
+        *journal.get_mut(a).unwrap() = "x";
+        assert_eq!(contents(&journal), vec![(0, "x")]);
+
+        assert!(journal.undo());
+        assert_eq!(contents(&journal), vec![(0, "a")]);
+
+        assert!(journal.undo());
+        assert!(journal.undo());
+        assert_eq!(contents(&journal), vec![(0, "a"), (2, "c")], "restoring the exact id");
+        assert!(!journal.as_id_vec().contains_id(b));
+
+        assert!(journal.undo());
+        assert_eq!(contents(&journal), vec![(0, "a"), (1, "b"), (2, "c")]);
+        assert!(journal.as_id_vec().is_packed());
+
+        while journal.undo() {}
+        assert!(journal.as_id_vec().is_empty());
+        assert!(!journal.can_undo());
+
+        while journal.redo() {}
+        assert_eq!(contents(&journal), vec![(0, "x")]);
+        assert!(!journal.can_redo());
+    }
+
+    #[test]
+    pub fn test_guard_records_only_modifications(){
+        let mut journal = JournaledIdVec::new();
+        let a = journal.insert("a");
+        journal.clear_history();
+
+        {
+            let guard = journal.get_mut(a).unwrap();
+            assert_eq!(*guard, "a");
+        }
+
+        assert!(!journal.can_undo(), "reading through the guard is not recorded");
+
+        {
+            let mut guard = journal.get_mut(a).unwrap();
+            *guard = "b";
+            *guard = "c";
+        }
+
+        assert!(journal.undo());
+        assert_eq!(contents(&journal), vec![(0, "a")], "recording the element before the first modification");
+        assert!(!journal.can_undo());
+        assert!(journal.get_mut(Id::from_index(1)).is_none());
+    }
+
+    #[test]
+    pub fn test_new_modification_discards_redo(){
+        let mut journal = JournaledIdVec::new();
+        journal.insert("a");
+        journal.insert("b");
+        journal.undo();
+        assert!(journal.can_redo());
+
+        journal.insert("c");
+        assert!(!journal.can_redo());
+        assert_eq!(contents(&journal), vec![(0, "a"), (1, "c")]);
+    }
+
+    #[test]
+    pub fn test_checkpoints(){
+        let mut journal = JournaledIdVec::new();
+        let a = journal.insert("a");
+        journal.checkpoint("one");
+        journal.insert("b");
+        journal.remove(a);
+        journal.checkpoint("two");
+
+        assert!(journal.restore_checkpoint("one"));
+        assert_eq!(contents(&journal), vec![(0, "a")]);
+
+        assert!(journal.restore_checkpoint("two"));
+        assert_eq!(contents(&journal), vec![(1, "b")]);
+
+        journal.restore_checkpoint("one");
+        journal.insert("c");
+        assert!(!journal.restore_checkpoint("two"), "checkpoint discarded by new modification");
+        assert!(!journal.restore_checkpoint("three"));
+    }
+
+    #[test]
+    pub fn test_history_limit(){
+        let mut journal = JournaledIdVec::with_history_limit(2);
+        journal.checkpoint("empty");
+        journal.insert("a");
+        journal.insert("b");
+        journal.insert("c");
+
+        assert!(!journal.restore_checkpoint("empty"), "checkpoint discarded by history limit");
+        assert!(journal.undo());
+        assert!(journal.undo());
+        assert!(!journal.undo());
+        assert_eq!(contents(&journal), vec![(0, "a")]);
+    }
+}
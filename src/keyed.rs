@@ -0,0 +1,94 @@
+
+use ::std::ops::{ Deref, DerefMut };
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Finds the ids of elements by a key derived from each element
+pub(crate) trait KeyIndex<T, K> {
+    fn add(&mut self, key: K, id: Id<T>);
+    fn remove(&mut self, key: &K, id: Id<T>);
+    fn clear(&mut self);
+}
+
+
+/// An `IdVec` whose elements are kept inside an index by their key.
+/// Shared by `IndexedIdVec` and `OrderedIdVec`, which only differ in the index.
+pub(crate) struct KeyedIdVec<T, K, I> {
+    pub(crate) elements: IdVec<T>,
+    pub(crate) index: I,
+    pub(crate) key: fn(&T) -> K,
+}
+
+
+impl<T, K, I> KeyedIdVec<T, K, I> where I: KeyIndex<T, K> {
+
+    /// Add all elements of an existing IdVec to the index, in the order of their ids
+    pub fn from_id_vec(elements: IdVec<T>, mut index: I, key: fn(&T) -> K) -> Self {
+        for (id, element) in elements.iter() {
+            index.add(key(element), id);
+        }
+
+        KeyedIdVec { elements, index, key }
+    }
+
+    /// Return a guard which allows modifying the element that this id points to
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<KeyGuard<'_, T, K>> where K: PartialEq {
+        let previous_key = (self.key)(self.elements.get(id)?);
+        Some(KeyGuard { elements: &mut self.elements, index: &mut self.index, key: self.key, id, previous_key })
+    }
+
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        let key = (self.key)(&element);
+        let id = self.elements.insert(element);
+        self.index.add(key, id);
+        id
+    }
+
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        let element = self.elements.remove(id)?;
+        self.index.remove(&(self.key)(&element), id);
+        Some(element)
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+        self.index.clear();
+    }
+}
+
+
+
+/// Allows modifying an element of an `IndexedIdVec` or an `OrderedIdVec`.
+/// Updates the index when dropped, if the key of the element has changed.
+pub struct KeyGuard<'s, T: 's, K: 's + PartialEq> {
+    elements: &'s mut IdVec<T>,
+    index: &'s mut dyn KeyIndex<T, K>,
+    key: fn(&T) -> K,
+    id: Id<T>,
+    previous_key: K,
+}
+
+impl<'s, T: 's, K: 's + PartialEq> Deref for KeyGuard<'s, T, K> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.elements[self.id]
+    }
+}
+
+impl<'s, T: 's, K: 's + PartialEq> DerefMut for KeyGuard<'s, T, K> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.elements[self.id]
+    }
+}
+
+impl<'s, T: 's, K: 's + PartialEq> Drop for KeyGuard<'s, T, K> {
+    fn drop(&mut self) {
+        let key = (self.key)(&self.elements[self.id]);
+
+        if key != self.previous_key {
+            self.index.remove(&self.previous_key, self.id);
+            self.index.add(key, self.id);
+        }
+    }
+}
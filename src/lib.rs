@@ -1,12 +1,82 @@
 // extern crate num_traits;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+extern crate memmap2;
+
 
 #[macro_use]
 pub mod vec;
 pub mod id;
+pub mod graph;
+pub mod traverse;
+pub mod gc;
+pub mod table;
+pub mod path;
+pub mod tree;
+pub mod validate;
+pub mod relation;
+pub mod rc;
+pub mod journal;
+pub mod snapshot;
+pub mod changes;
+pub mod patch;
+pub mod observe;
+pub mod transaction;
+pub mod replicated;
+pub mod binary;
+pub mod stream;
+pub mod bundle;
+pub mod migrate;
+pub mod durable;
+pub mod indexed;
+pub mod ordered;
+pub mod read;
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub mod mmap;
+
+mod bits;
+mod keyed;
+
+#[cfg(feature = "serde")]
+mod serialize;
 
 pub use vec::IdVec;
 pub use id::Id;
+pub use graph::Graph;
+pub use traverse::Neighbors;
+pub use gc::Trace;
+pub use table::IdTable;
+pub use tree::IdTree;
+pub use validate::References;
+pub use relation::{ Relation, Relations, OnDelete };
+pub use rc::{ RcIdVec, Handle, WeakId };
+pub use journal::JournaledIdVec;
+pub use snapshot::{ SnapshotIdVec, Snapshot };
+pub use changes::Changes;
+pub use patch::IdVecPatch;
+pub use observe::ObservedIdVec;
+pub use transaction::Transaction;
+pub use replicated::{ ReplicatedIdVec, ReplicatedId };
+pub use binary::{ Encode, Decode };
+pub use stream::{ IdVecWriter, IdVecReader };
+pub use bundle::{ Bundle, CrossReferences, IdVisitor, IdVisitorMut };
+pub use migrate::{ Versioned, Migrations };
+pub use durable::DurableIdVec;
+pub use indexed::IndexedIdVec;
+pub use ordered::OrderedIdVec;
+pub use read::ReadIdVec;
+pub use keyed::KeyGuard;
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub use mmap::{ MmapIdVec, Pod };
 
 #[cfg(test)]
 mod examples {
@@ -16,6 +86,7 @@ mod examples {
     fn nodes() {
 
         #[derive(Debug)]
+        #[allow(dead_code)]
         struct Node {
             parent: Option<Id<Node>>,
             name: String,
@@ -71,6 +142,40 @@ mod examples {
     }
 
 
+    #[test]
+    fn garbage_collection() {
+
+        struct Node {
+            parent: Option<Id<Node>>,
+        }
+
+        impl Trace for Node {
+            fn trace<F: FnMut(Id<Self>)>(&self, mut visit: F) {
+                if let Some(parent) = self.parent {
+                    visit(parent);
+                }
+            }
+        }
+
+
+
+        let mut nodes = IdVec::new();
+        let root = nodes.insert(Node { parent: None });
+        let child = nodes.insert(Node { parent: Some(root) });
+
+        // create an unreachable cycle
+        let a = nodes.insert(Node { parent: None });
+        let b = nodes.insert(Node { parent: Some(a) });
+        nodes[a].parent = Some(b);
+
+        // keep the child and everything it points to
+        let removed = nodes.collect_garbage(vec![ child ]);
+        assert_eq!(removed, vec![ a, b ]);
+        assert!(nodes.contains_id(root));
+        assert!(nodes.contains_id(child));
+    }
+
+
     #[test]
     fn example1() {
         let map = id_vec!("hello", "world");
@@ -0,0 +1,284 @@
+
+use ::std::collections::HashMap;
+use ::std::io::{ self, Read, Write };
+use ::id::*;
+use ::vec::IdVec;
+use ::binary::{ Encode, Decode, DecodeError, write_header, read_header, write_length, read_index, write_slots, read_slots, check_writable_slots };
+
+
+/// The first bytes of every IdVec written by `write_versioned_to`
+const MAGIC: [u8; 4] = *b"IDVV";
+
+
+/// An element type whose encoding may change between releases.
+/// Increase the version whenever the encoding changes, and register a migration step from the previous version.
+pub trait Versioned: Encode + Decode {
+    const VERSION: u32;
+}
+
+
+/// Converts the encoded bytes of an element from one version into the encoded bytes of the next version
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, DecodeError>;
+
+/// The migration steps of an element type, each converting from one version to the next.
+/// Old elements are migrated by applying all steps up to the current version in sequence,
+/// so increasing the version only requires a single new step.
+/// Example: `Migrations::new().with(1, |bytes| { let mut bytes = bytes.to_vec(); 1.0_f32.encode(&mut bytes)?; Ok(bytes) })`
+pub struct Migrations<T> {
+    steps: HashMap<u32, Migration>,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+
+impl<T> Migrations<T> where T: Versioned {
+
+    /// Does not allocate heap memory. Only elements of the current version can be read.
+    pub fn new() -> Self {
+        Migrations { steps: HashMap::new(), _marker: ::std::marker::PhantomData }
+    }
+
+    /// Register the migration from the specified version to the next version,
+    /// replacing any previous migration of that version
+    pub fn with(mut self, version: u32, step: Migration) -> Self {
+        debug_assert!(version < T::VERSION, "Migrating from the current or a future version");
+        self.steps.insert(version, step);
+        self
+    }
+
+    /// Whether elements of this version can be read, requiring a step for each version up to the current one
+    pub fn supports(&self, version: u32) -> bool {
+        version <= T::VERSION && (version .. T::VERSION).all(|step| self.steps.contains_key(&step))
+    }
+
+    /// Decode a single element of the specified version, which must consume all bytes
+    pub fn decode(&self, version: u32, bytes: &[u8]) -> Result<T, DecodeError> {
+        if version > T::VERSION {
+            return Err(DecodeError::UnsupportedElementVersion(version));
+        }
+
+        let mut migrated: Option<Vec<u8>> = None;
+        for step_version in version .. T::VERSION {
+            let step = self.steps.get(&step_version).ok_or(DecodeError::UnsupportedElementVersion(version))?;
+            migrated = Some(step(migrated.as_ref().map_or(bytes, Vec::as_slice))?);
+        }
+
+        let mut remaining = migrated.as_ref().map_or(bytes, Vec::as_slice);
+        let element = T::decode(&mut remaining)?;
+
+        if !remaining.is_empty() {
+            return Err(DecodeError::InvalidData("element contains trailing bytes"));
+        }
+
+        Ok(element)
+    }
+}
+
+impl<T> Default for Migrations<T> where T: Versioned {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Read exactly `length` bytes without trusting the length for allocation
+fn read_bytes<R: Read>(read: &mut R, length: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes = Vec::new();
+    read.take(length as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != length {
+        return Err(DecodeError::Truncated);
+    }
+
+    Ok(bytes)
+}
+
+
+impl<T> IdVec<T> where T: Versioned {
+
+    /// Like `write_to`, but additionally stores the version of the element type,
+    /// and the length of each element, so that elements can be migrated when the type changes.
+    /// Like `write_to`, fails without writing if there are more than a million unused slots, and fewer used slots.
+    pub fn write_versioned_to<W: Write>(&self, mut write: W) -> io::Result<()> {
+        check_writable_slots(self)?;
+        write_header(&mut write, MAGIC)?;
+        T::VERSION.encode(&mut write)?;
+        write_slots(&mut write, self)?;
+
+        let mut bytes = Vec::new();
+        for element in self.elements() {
+            bytes.clear();
+            element.encode(&mut bytes)?;
+            write_length(&mut write, bytes.len() as u64)?;
+            write.write_all(&bytes)?;
+        }
+
+        write.flush()
+    }
+
+    /// Read ids and elements which were written by `write_versioned_to`,
+    /// possibly with an older version of the element type.
+    /// Old elements are migrated, keeping the ids of all elements.
+    pub fn read_versioned_from<R: Read>(mut read: R, migrations: &Migrations<T>) -> Result<Self, DecodeError> {
+        read_header(&mut read, MAGIC)?;
+
+        let version = u32::decode(&mut read)?;
+        if !migrations.supports(version) {
+            return Err(DecodeError::UnsupportedElementVersion(version));
+        }
+
+        let used_ranges = read_slots(&mut read)?;
+        let mut vec = IdVec::new();
+
+        for index in used_ranges.into_iter().flatten() {
+            let length = read_index(&mut read)?;
+            let bytes = read_bytes(&mut read, length)?;
+            vec.insert_at(Id::from_index(index), migrations.decode(version, &bytes)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The first release, only storing a name
+    #[derive(Debug, PartialEq)]
+    struct NodeV1 { name: String }
+
+    /// The second release, additionally storing a weight
+    #[derive(Debug, PartialEq)]
+    struct NodeV2 { name: String, weight: f32 }
+
+    /// The current release, additionally storing the visibility
+    #[derive(Debug, PartialEq)]
+    struct Node { name: String, weight: f32, visible: bool }
+
+    impl Encode for NodeV1 {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.name.encode(write)
+        }
+    }
+
+    impl Decode for NodeV1 {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(NodeV1 { name: String::decode(read)? })
+        }
+    }
+
+    impl Versioned for NodeV1 {
+        const VERSION: u32 = 1;
+    }
+
+    impl Encode for NodeV2 {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.name.encode(write)?;
+            self.weight.encode(write)
+        }
+    }
+
+    impl Decode for NodeV2 {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(NodeV2 { name: String::decode(read)?, weight: f32::decode(read)? })
+        }
+    }
+
+    impl Versioned for NodeV2 {
+        const VERSION: u32 = 2;
+    }
+
+    impl Encode for Node {
+        fn encode<W: Write>(&self, write: &mut W) -> io::Result<()> {
+            self.name.encode(write)?;
+            self.weight.encode(write)?;
+            self.visible.encode(write)
+        }
+    }
+
+    impl Decode for Node {
+        fn decode<R: Read>(read: &mut R) -> Result<Self, DecodeError> {
+            Ok(Node { name: String::decode(read)?, weight: f32::decode(read)?, visible: bool::decode(read)? })
+        }
+    }
+
+    impl Versioned for Node {
+        const VERSION: u32 = 3;
+    }
+
+    /// Appends a default weight
+    fn v1_to_v2(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let old = NodeV1::decode(&mut &bytes[..])?;
+        let mut new = Vec::new();
+        NodeV2 { name: old.name, weight: 1.0 }.encode(&mut new)?;
+        Ok(new)
+    }
+
+    /// Appends the default visibility
+    fn v2_to_v3(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut new = bytes.to_vec();
+        true.encode(&mut new)?;
+        Ok(new)
+    }
+
+    fn migrations() -> Migrations<Node> {
+        Migrations::new().with(1, v1_to_v2).with(2, v2_to_v3)
+    }
+
+    fn node(name: &str, weight: f32, visible: bool) -> Node {
+        Node { name: String::from(name), weight, visible }
+    }
+
+    #[test]
+    pub fn test_migrate_keeps_ids(){
+        let mut old = IdVec::new();
+        let removed = old.insert(NodeV1 { name: String::from("removed") });
+        let kept = old.insert(NodeV1 { name: String::from("kept") });
+        old.remove(removed);
+
+        let mut bytes = Vec::new();
+        old.write_versioned_to(&mut bytes).unwrap();
+
+        let migrated = IdVec::<Node>::read_versioned_from(bytes.as_slice(), &migrations()).unwrap();
+        assert_eq!(migrated.len(), 1);
+        assert!(!migrated.contains_id(Id::from_index(removed.index_value())));
+        assert_eq!(migrated[Id::from_index(kept.index_value())], node("kept", 1.0, true), "chaining both steps");
+    }
+
+    #[test]
+    pub fn test_current_version_and_missing_migration(){
+        let current = id_vec!(node("a", 3.0, false));
+        let mut bytes = Vec::new();
+        current.write_versioned_to(&mut bytes).unwrap();
+        assert_eq!(IdVec::read_versioned_from(bytes.as_slice(), &Migrations::new()).unwrap(), current);
+
+        let mut v2_bytes = Vec::new();
+        id_vec!(NodeV2 { name: String::from("b"), weight: 2.0 }).write_versioned_to(&mut v2_bytes).unwrap();
+        let only_last_step = Migrations::new().with(2, v2_to_v3);
+        let from_v2 = IdVec::<Node>::read_versioned_from(v2_bytes.as_slice(), &only_last_step).unwrap();
+        assert_eq!(from_v2[Id::from_index(0)], node("b", 2.0, true));
+
+        let mut v1_bytes = Vec::new();
+        id_vec!(NodeV1 { name: String::from("a") }).write_versioned_to(&mut v1_bytes).unwrap();
+        let result = IdVec::<Node>::read_versioned_from(v1_bytes.as_slice(), &only_last_step);
+        assert!(matches!(result, Err(DecodeError::UnsupportedElementVersion(1))), "missing the first step");
+        assert!(!migrations().supports(4));
+
+        let truncated = IdVec::<Node>::read_versioned_from(&bytes[.. bytes.len() - 1], &migrations());
+        assert!(matches!(truncated, Err(DecodeError::Truncated)));
+    }
+
+    #[test]
+    pub fn test_huge_unused_run(){
+        // version 3, an id index limit of 2^62, with runs of 0 used, 2^62 - 1 unused, and 1 used slot
+        let mut hostile = b"IDVV\x01\x00\x03\x00\x00\x00".to_vec();
+        for &length in &[1_u64 << 62, 3, 0, (1 << 62) - 1, 1, 0] {
+            write_length(&mut hostile, length).unwrap();
+        }
+
+        let result = IdVec::<Node>::read_versioned_from(hostile.as_slice(), &migrations());
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+    }
+}
@@ -0,0 +1,571 @@
+
+use ::std::fs::{ File, OpenOptions, TryLockError };
+use ::std::io;
+use ::std::mem::{ size_of, align_of };
+use ::std::path::Path;
+use ::std::slice;
+use ::memmap2::MmapMut;
+use ::id::*;
+use ::vec::IdVec;
+use ::read::ReadIdVec;
+
+
+/// The first bytes of every file used by `MmapIdVec`
+const MAGIC: [u8; 4] = *b"IDVM";
+
+/// The current version of the file layout, increased on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+
+/// The header is padded to this size, so that elements with an alignment up to this size follow directly
+const HEADER_SIZE: usize = 64;
+
+/// The number of slots of a new file. The capacity is always a multiple of the bits per word.
+const INITIAL_CAPACITY: usize = 64;
+
+const WORD_BITS: usize = 64;
+
+/// The mapping starts at a page boundary, which guarantees this alignment
+const MAX_ALIGNMENT: usize = 4096;
+
+
+/// Plain old data, which can be stored inside a memory-mapped file and read back directly.
+///
+/// # Safety
+/// Every bit pattern must be a valid value of the type, the type must not contain padding bytes,
+/// and it must not contain any pointers or references.
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for usize {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for i128 {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+
+/// Stored at the start of the file, in native byte order
+#[repr(C)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+    element_size: u64,
+    element_align: u64,
+
+    /// The number of slots which fit into the file
+    capacity: u64,
+
+    /// The id index limit, the last of these slots is always used
+    slot_count: u64,
+
+    /// The number of used slots
+    len: u64,
+}
+
+
+/// An IdVec whose slots and occupancy bitmap are stored inside a memory-mapped file.
+/// Opening a file does not read the elements, and modifications are written back by the operating system,
+/// or explicitly by calling `flush`. The file can only be read on machines with the same byte order.
+/// After a crash, modifications since the last `flush` may be partially written,
+/// which is only detected when opening if the header and the bitmap disagree.
+///
+/// The file consists of a header, followed by all slots, followed by the occupancy bitmap.
+/// Removed slots are reused by the next insertion, lowest id first.
+///
+/// Offers the `get` and `iter` methods of an IdVec, and implements `ReadIdVec`,
+/// so the graph traversals and shortest path searches run directly on the mapped file.
+///
+/// This is the only part of this library which uses unsafe code, and it is only available with the `mmap` feature.
+pub struct MmapIdVec<T> {
+    file: File,
+    map: MmapMut,
+
+    /// All slots below this index are known to be used
+    search_start: Index,
+
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+fn slots_offset<T>() -> usize {
+    align_up(HEADER_SIZE, align_of::<T>())
+}
+
+fn bitmap_offset<T>(capacity: usize) -> usize {
+    align_up(slots_offset::<T>() + capacity * size_of::<T>(), size_of::<u64>())
+}
+
+fn file_size<T>(capacity: usize) -> usize {
+    bitmap_offset::<T>(capacity) + capacity / WORD_BITS * size_of::<u64>()
+}
+
+fn invalid_file(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid mapped IdVec file: {}", reason))
+}
+
+
+impl<T> MmapIdVec<T> where T: Pod {
+
+    /// Open the file, or create it if it does not exist.
+    /// Fails if the file was created for a type with a different size or alignment,
+    /// or if the file is already opened by another `MmapIdVec`, which holds an exclusive lock on it.
+    ///
+    /// # Safety
+    /// While the vector is open, the file must not be modified or truncated by anything else than this vector,
+    /// neither by this process nor by other processes. The lock only prevents other `MmapIdVec`s from opening the file,
+    /// as other programs may ignore it. Otherwise, references to elements could observe changing or missing memory.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        assert!(align_of::<T>() <= MAX_ALIGNMENT, "Element alignment exceeds the page size");
+
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+
+        file.try_lock().map_err(|error| match error {
+            TryLockError::WouldBlock => io::Error::new(io::ErrorKind::WouldBlock, "mapped IdVec file is already opened"),
+            TryLockError::Error(error) => error,
+        })?;
+
+        let is_new = file.metadata()?.len() == 0;
+
+        if is_new {
+            file.set_len(file_size::<T>(INITIAL_CAPACITY) as u64)?;
+        }
+
+        // safety: the caller guarantees that the file is only accessed through this vector, which owns the locked file handle
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        let mut vec = MmapIdVec { file, map, search_start: 0, _marker: ::std::marker::PhantomData };
+
+        if is_new {
+            *vec.header_mut() = Header {
+                magic: MAGIC, version: FORMAT_VERSION,
+                element_size: size_of::<T>() as u64, element_align: align_of::<T>() as u64,
+                capacity: INITIAL_CAPACITY as u64, slot_count: 0, len: 0,
+            };
+        }
+
+        vec.validate()?;
+        Ok(vec)
+    }
+
+    /// Check the header and the bitmap, without reading any slots
+    fn validate(&self) -> io::Result<()> {
+        if self.map.len() < HEADER_SIZE { return Err(invalid_file("missing header")); }
+
+        let header = self.header();
+        if header.magic != MAGIC { return Err(invalid_file("missing magic bytes")); }
+        if header.version != FORMAT_VERSION { return Err(invalid_file("unsupported version")); }
+
+        if header.element_size != size_of::<T>() as u64 || header.element_align != align_of::<T>() as u64 {
+            return Err(invalid_file("element type does not match"));
+        }
+
+        // bounding the capacity by the file length first prevents overflowing when computing the file size
+        let capacity = header.capacity as usize;
+        if header.capacity > self.map.len() as u64 || !capacity.is_multiple_of(WORD_BITS)
+            || self.map.len() < file_size::<T>(capacity)
+        {
+            return Err(invalid_file("file is smaller than its capacity"));
+        }
+
+        if header.slot_count > header.capacity || header.len > header.slot_count {
+            return Err(invalid_file("invalid length"));
+        }
+
+        let slot_count = header.slot_count as usize;
+        let words = self.words();
+
+        let first_word = slot_count / WORD_BITS;
+        let beyond_slot_count = words.get(first_word).is_some_and(|&word| word >> (slot_count % WORD_BITS) != 0)
+            || words.iter().skip(first_word + 1).any(|&word| word != 0);
+
+        if beyond_slot_count || (slot_count > 0 && !self.contains_id(Id::from_index(slot_count - 1))) {
+            return Err(invalid_file("slot count does not end with the last used slot"));
+        }
+
+        let used_count: u64 = words.iter().map(|word| u64::from(word.count_ones())).sum();
+        if used_count != header.len {
+            return Err(invalid_file("length does not match the used slots"));
+        }
+
+        Ok(())
+    }
+
+    fn header(&self) -> &Header {
+        // safety: the mapping is page-aligned and at least as large as the header
+        unsafe { &*(self.map.as_ptr() as *const Header) }
+    }
+
+    fn header_mut(&mut self) -> &mut Header {
+        unsafe { &mut *(self.map.as_mut_ptr() as *mut Header) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.header().capacity as usize
+    }
+
+    fn words(&self) -> &[u64] {
+        let capacity = self.capacity();
+
+        // safety: validated file size, and aligned offset inside the page-aligned mapping
+        unsafe {
+            let words = self.map.as_ptr().add(bitmap_offset::<T>(capacity)) as *const u64;
+            slice::from_raw_parts(words, capacity / WORD_BITS)
+        }
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        let capacity = self.capacity();
+
+        unsafe {
+            let words = self.map.as_mut_ptr().add(bitmap_offset::<T>(capacity)) as *mut u64;
+            slice::from_raw_parts_mut(words, capacity / WORD_BITS)
+        }
+    }
+
+    fn slots(&self) -> &[T] {
+        // safety: validated file size, aligned offset, and every bit pattern is a valid `Pod`
+        unsafe {
+            let slots = self.map.as_ptr().add(slots_offset::<T>()) as *const T;
+            slice::from_raw_parts(slots, self.capacity())
+        }
+    }
+
+    fn slots_mut(&mut self) -> &mut [T] {
+        unsafe {
+            let slots = self.map.as_mut_ptr().add(slots_offset::<T>()) as *mut T;
+            slice::from_raw_parts_mut(slots, self.capacity())
+        }
+    }
+
+    fn set_used(&mut self, index: Index, used: bool) {
+        let bit = 1 << (index % WORD_BITS);
+        let word = &mut self.words_mut()[index / WORD_BITS];
+        if used { *word |= bit } else { *word &= !bit }
+    }
+
+    /// Double the capacity, moving the bitmap behind the new slots
+    fn grow(&mut self) -> io::Result<()> {
+        let old_capacity = self.capacity();
+        let new_capacity = (old_capacity * 2).max(INITIAL_CAPACITY);
+        let old_bitmap = bitmap_offset::<T>(old_capacity) .. file_size::<T>(old_capacity);
+
+        self.map.flush()?;
+        self.file.set_len(file_size::<T>(new_capacity) as u64)?;
+        self.map = unsafe { MmapMut::map_mut(&self.file)? };
+
+        // the new bitmap starts behind the old file end, so the new words are zero
+        self.map.copy_within(old_bitmap, bitmap_offset::<T>(new_capacity));
+
+        // pages are written back in any order, so the new bitmap must be on disk before the new capacity,
+        // and a crash keeps either the complete old or the complete new layout
+        self.map.flush()?;
+        self.header_mut().capacity = new_capacity as u64;
+        Ok(())
+    }
+
+    /// The lowest unused index, or the id index limit if all slots are used
+    fn find_unused(&mut self) -> Index {
+        let slot_count = self.id_index_limit();
+
+        while self.search_start < slot_count {
+            let index = self.search_start;
+            let lower_bits = (1_u64 << (index % WORD_BITS)) - 1;
+            let word = self.words()[index / WORD_BITS] | lower_bits;
+
+            if word != u64::MAX {
+                self.search_start = (index - index % WORD_BITS + (!word).trailing_zeros() as usize).min(slot_count);
+                return self.search_start;
+            }
+
+            self.search_start = index - index % WORD_BITS + WORD_BITS;
+        }
+
+        self.search_start = slot_count;
+        slot_count
+    }
+
+
+
+    pub fn len(&self) -> usize {
+        self.header().len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This vector will not contain an id with an index value greater than or equal to this value.
+    pub fn id_index_limit(&self) -> usize {
+        self.header().slot_count as usize
+    }
+
+    /// Excludes deleted elements, and indices out of range
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        let index = id.index_value();
+        index < self.id_index_limit() && self.words()[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        if self.contains_id(id) { Some(&self.slots()[id.index_value()]) }
+        else { None }
+    }
+
+    /// Return a mutable reference to the element that this id points to
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        if self.contains_id(id) { Some(&mut self.slots_mut()[id.index_value()]) }
+        else { None }
+    }
+
+    /// Used for immutable access to ids and elements
+    pub fn iter(&self) -> impl Iterator<Item = (Id<T>, &T)> + '_ {
+        let slots = self.slots();
+        (0 .. self.id_index_limit()).map(Id::from_index)
+            .filter(move |&id| self.contains_id(id))
+            .map(move |id| (id, &slots[id.index_value()]))
+    }
+
+    /// Used for immutable indirect access
+    pub fn ids(&self) -> impl Iterator<Item = Id<T>> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+
+    /// Associate the specified element with the lowest unused id.
+    /// Fails if the file cannot be grown.
+    pub fn insert(&mut self, element: T) -> io::Result<Id<T>> {
+        let index = self.find_unused();
+        if index == self.capacity() {
+            self.grow()?;
+        }
+
+        self.slots_mut()[index] = element;
+        self.set_used(index, true);
+
+        let header = self.header_mut();
+        header.len += 1;
+        header.slot_count = header.slot_count.max(index as u64 + 1);
+        Ok(Id::from_index(index))
+    }
+
+    /// Enable the specified id to be overwritten when a new element is inserted.
+    /// Returns the removed element. Ignores invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        let element = *self.get(id)?;
+        let index = id.index_value();
+
+        self.set_used(index, false);
+        self.search_start = self.search_start.min(index);
+        self.header_mut().len -= 1;
+
+        // keep the last slot used, like the IdVec
+        let mut slot_count = self.id_index_limit();
+        while slot_count > 0 && !self.contains_id(Id::from_index(slot_count - 1)) {
+            slot_count -= 1;
+            self.header_mut().slot_count = slot_count as u64;
+        }
+
+        Some(element)
+    }
+
+    /// Write all modifications to the file, blocking until they are on disk
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+
+    /// Copy all elements into an IdVec, keeping their ids
+    pub fn to_id_vec(&self) -> IdVec<T> {
+        let mut vec = IdVec::with_capacity(self.id_index_limit());
+        for (id, &element) in self.iter() {
+            vec.insert_at(id, element);
+        }
+
+        vec
+    }
+}
+
+impl<T> ReadIdVec<T> for MmapIdVec<T> where T: Pod {
+    fn get(&self, id: Id<T>) -> Option<&T> {
+        MmapIdVec::get(self, id)
+    }
+
+    fn contains_id(&self, id: Id<T>) -> bool {
+        MmapIdVec::contains_id(self, id)
+    }
+
+    fn iter<'s>(&'s self) -> impl Iterator<Item = (Id<T>, &'s T)> + 's where T: 's {
+        MmapIdVec::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        MmapIdVec::len(self)
+    }
+
+    fn id_index_limit(&self) -> usize {
+        MmapIdVec::id_index_limit(self)
+    }
+}
+
+impl<T> ::std::ops::Index<Id<T>> for MmapIdVec<T> where T: Pod {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("Indexing with invalid Id: `{:?}` ", id))
+    }
+}
+
+impl<T> ::std::ops::IndexMut<Id<T>> for MmapIdVec<T> where T: Pod {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        self.get_mut(id).unwrap_or_else(|| panic!("Indexing-Mut with invalid Id: `{:?}` ", id))
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::fs;
+    use ::std::path::PathBuf;
+
+    /// A path inside the temporary directory of the system, which does not exist yet
+    fn temporary_file(name: &str) -> PathBuf {
+        let path = ::std::env::temp_dir().join(format!("id-vec-mmap-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    pub fn test_persist_and_reopen(){
+        let path = temporary_file("reopen");
+
+        {
+            let mut vec = unsafe { MmapIdVec::<[f32; 3]>::open(&path) }.unwrap();
+            let a = vec.insert([1.0, 2.0, 3.0]).unwrap();
+            let b = vec.insert([4.0, 5.0, 6.0]).unwrap();
+            vec.insert([7.0, 8.0, 9.0]).unwrap();
+            vec[a][0] = 10.0;
+            assert_eq!(vec.remove(b), Some([4.0, 5.0, 6.0]));
+            vec.flush().unwrap();
+        }
+
+        let mut vec = unsafe { MmapIdVec::<[f32; 3]>::open(&path) }.unwrap();
+        assert_eq!(vec.len(), 2);
+
+        let opened_twice = unsafe { MmapIdVec::<[f32; 3]>::open(&path) };
+        assert_eq!(opened_twice.err().map(|error| error.kind()), Some(io::ErrorKind::WouldBlock), "locked while opened");
+
+        assert_eq!(vec[Id::from_index(0)], [10.0, 2.0, 3.0]);
+        assert!(!vec.contains_id(Id::from_index(1)));
+        assert_eq!(vec.insert([0.0; 3]).unwrap(), Id::from_index(1), "reusing removed slot");
+
+        drop(vec);
+        assert!(unsafe { MmapIdVec::<u8>::open(&path) }.is_err(), "different element type");
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Points to the next element of a linked list, or to itself at the end
+    #[derive(Clone, Copy)]
+    struct Link { next: u64 }
+    unsafe impl Pod for Link {}
+
+    impl ::traverse::Neighbors for Link {
+        fn visit_neighbors<F: FnMut(Id<Self>)>(&self, mut visit: F) {
+            visit(Id::from_index(self.next as usize))
+        }
+    }
+
+    #[test]
+    pub fn test_algorithms_on_mapped_file(){
+        let path = temporary_file("algorithms");
+        let mut vec = unsafe { MmapIdVec::<Link>::open(&path) }.unwrap();
+        vec.insert(Link { next: 2 }).unwrap();
+        vec.insert(Link { next: 0 }).unwrap();
+        vec.insert(Link { next: 2 }).unwrap();
+
+        let order = ::traverse::Dfs::new(&vec, Id::from_index(1)).map(|id| id.index_value()).collect::<Vec<_>>();
+        assert_eq!(order, vec![ 1, 0, 2 ]);
+        assert_eq!(::traverse::find_cycle(&vec), Some(vec![ Id::from_index(2) ]));
+
+        vec[Id::from_index(2)].next = 1;
+        assert_eq!(::traverse::strongly_connected_components(&vec).len(), 1, "all elements form one cycle");
+
+        drop(vec);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_grow_and_shrink(){
+        let path = temporary_file("grow");
+        let mut vec = unsafe { MmapIdVec::<u64>::open(&path) }.unwrap();
+
+        for index in 0 .. 200 {
+            assert_eq!(vec.insert(index).unwrap(), Id::from_index(index as usize));
+        }
+
+        for index in (50 .. 200).rev() {
+            vec.remove(Id::from_index(index));
+        }
+
+        vec.remove(Id::from_index(10));
+        assert_eq!(vec.id_index_limit(), 50, "removing unused slots at the back");
+
+        let expected: IdVec<u64> = {
+            let mut expected = (0 .. 50).collect::<IdVec<_>>();
+            expected.remove(Id::from_index(10));
+            expected
+        };
+
+        assert_eq!(vec.to_id_vec(), expected);
+        assert_eq!(vec.iter().map(|(_, &element)| element).sum::<u64>(), expected.elements().sum::<u64>());
+
+        drop(vec);
+        assert_eq!(unsafe { MmapIdVec::<u64>::open(&path) }.unwrap().to_id_vec(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_inconsistent_header(){
+        let path = temporary_file("inconsistent");
+
+        {
+            let mut vec = unsafe { MmapIdVec::<u32>::open(&path) }.unwrap();
+            let removed = vec.insert(1).unwrap();
+            vec.insert(2).unwrap();
+            vec.remove(removed);
+            vec.flush().unwrap();
+        }
+
+        let valid = fs::read(&path).unwrap();
+        let slot_count_bytes = 32 .. 40;
+        let len_bytes = 40 .. 48;
+
+        let mut wrong_len = valid.clone();
+        wrong_len[len_bytes].copy_from_slice(&0_u64.to_ne_bytes());
+
+        let mut shorter_slot_count = valid.clone();
+        shorter_slot_count[slot_count_bytes.clone()].copy_from_slice(&1_u64.to_ne_bytes());
+
+        let mut longer_slot_count = valid.clone();
+        longer_slot_count[slot_count_bytes].copy_from_slice(&3_u64.to_ne_bytes());
+
+        for corrupted in &[wrong_len, shorter_slot_count, longer_slot_count] {
+            fs::write(&path, corrupted).unwrap();
+            let result = unsafe { MmapIdVec::<u32>::open(&path) };
+            assert_eq!(result.err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+        }
+
+        fs::write(&path, &valid).unwrap();
+        assert_eq!(unsafe { MmapIdVec::<u32>::open(&path) }.unwrap().len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
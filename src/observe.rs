@@ -0,0 +1,260 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Called with the id and the element that has been inserted or removed
+type ElementListener<T> = Box<dyn FnMut(Id<T>, &T)>;
+
+/// Called with the old and the new id of an element moved by `pack`
+type MoveListener<T> = Box<dyn FnMut(Id<T>, Id<T>)>;
+
+
+/// Identifies a registered listener, used to unregister it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListenerId(usize);
+
+
+/// An `IdVec` which notifies registered listeners about inserted, removed and moved elements.
+/// Allows caches and indices to stay in sync with the vector, without modifying every call site.
+/// Listeners are called in the order of registration, after the modification has been applied.
+pub struct ObservedIdVec<T> {
+    elements: IdVec<T>,
+    next_listener: usize,
+    insert_listeners: Vec<(ListenerId, ElementListener<T>)>,
+    remove_listeners: Vec<(ListenerId, ElementListener<T>)>,
+    move_listeners: Vec<(ListenerId, MoveListener<T>)>,
+}
+
+
+fn notify<T>(listeners: &mut [(ListenerId, ElementListener<T>)], id: Id<T>, element: &T) {
+    for (_, listener) in listeners {
+        listener(id, element);
+    }
+}
+
+
+impl<T> ObservedIdVec<T> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        Self::from_id_vec(IdVec::new())
+    }
+
+    /// Start observing an existing IdVec. The existing elements are not reported as inserted.
+    pub fn from_id_vec(elements: IdVec<T>) -> Self {
+        ObservedIdVec {
+            elements, next_listener: 0,
+            insert_listeners: Vec::new(),
+            remove_listeners: Vec::new(),
+            move_listeners: Vec::new(),
+        }
+    }
+
+    /// Read-only access to the current state
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.elements
+    }
+
+    /// Stop observing, dropping all listeners
+    pub fn into_id_vec(self) -> IdVec<T> {
+        self.elements
+    }
+
+    fn next_listener_id(&mut self) -> ListenerId {
+        self.next_listener += 1;
+        ListenerId(self.next_listener - 1)
+    }
+
+    /// Call the listener whenever an element is inserted
+    pub fn on_insert<F>(&mut self, listener: F) -> ListenerId where F: FnMut(Id<T>, &T) + 'static {
+        let id = self.next_listener_id();
+        self.insert_listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Call the listener whenever an element is removed, before the element is returned or dropped
+    pub fn on_remove<F>(&mut self, listener: F) -> ListenerId where F: FnMut(Id<T>, &T) + 'static {
+        let id = self.next_listener_id();
+        self.remove_listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Call the listener with the old and the new id whenever `pack` moves an element
+    pub fn on_move<F>(&mut self, listener: F) -> ListenerId where F: FnMut(Id<T>, Id<T>) + 'static {
+        let id = self.next_listener_id();
+        self.move_listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Unregister the listener. Returns false if it was not registered.
+    pub fn remove_listener(&mut self, listener: ListenerId) -> bool {
+        let count = self.listener_count();
+        self.insert_listeners.retain(|(id, _)| *id != listener);
+        self.remove_listeners.retain(|(id, _)| *id != listener);
+        self.move_listeners.retain(|(id, _)| *id != listener);
+        self.listener_count() != count
+    }
+
+    fn listener_count(&self) -> usize {
+        self.insert_listeners.len() + self.remove_listeners.len() + self.move_listeners.len()
+    }
+
+
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Excludes deleted elements, and indices out of range
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        self.elements.contains_id(id)
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        self.elements.get(id)
+    }
+
+    /// Return a mutable reference to the element that this id points to.
+    /// Modifications are not reported to any listener.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        self.elements.get_mut(id)
+    }
+
+    /// Used for immutable access to ids and elements
+    pub fn iter(&self) -> ::vec::Iter<'_, T> {
+        self.elements.iter()
+    }
+
+    /// Used for mutable access to ids and elements.
+    /// Modifications are not reported to any listener.
+    pub fn iter_mut(&mut self) -> ::vec::IterMut<'_, T> {
+        self.elements.iter_mut()
+    }
+
+
+
+    /// Associate the specified element with a currently unused id, notifying the insert listeners
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        let id = self.elements.insert(element);
+        notify(&mut self.insert_listeners, id, &self.elements[id]);
+        id
+    }
+
+    /// Remove the element, notifying the remove listeners.
+    /// Ignores invalid and deleted ids, returning `None`.
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        let element = self.elements.remove(id)?;
+        notify(&mut self.remove_listeners, id, &element);
+        Some(element)
+    }
+
+    /// Remove the element with the highest id, notifying the remove listeners
+    pub fn pop(&mut self) -> Option<(Id<T>, T)> {
+        let (id, element) = self.elements.pop()?;
+        notify(&mut self.remove_listeners, id, &element);
+        Some((id, element))
+    }
+
+    /// Retain only the elements specified by the predicate, notifying the remove listeners
+    pub fn retain<F>(&mut self, predicate: F) where F: Fn(Id<T>, &T) -> bool {
+        for id in self.elements.get_ids() {
+            if !predicate(id, &self.elements[id]) {
+                self.remove(id);
+            }
+        }
+    }
+
+    /// Remove all elements, notifying the remove listeners for each of them
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Make this map have a continuous flow of indices, notifying the move listeners
+    /// for every element that has been moved to a new id
+    pub fn pack(&mut self) {
+        let listeners = &mut self.move_listeners;
+
+        self.elements.pack(|old_id, new_id| {
+            for (_, listener) in listeners.iter_mut() {
+                listener(old_id, new_id);
+            }
+        });
+    }
+}
+
+impl<T> Default for ObservedIdVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ::std::ops::Index<Id<T>> for ObservedIdVec<T> {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        &self.elements[id]
+    }
+}
+
+impl<T> ::std::ops::IndexMut<Id<T>> for ObservedIdVec<T> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        &mut self.elements[id]
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::rc::Rc;
+    use ::std::cell::RefCell;
+
+    #[test]
+    pub fn test_insert_and_remove_listeners(){
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut vec = ObservedIdVec::new();
+
+        let inserted = events.clone();
+        vec.on_insert(move |id, &element| inserted.borrow_mut().push(("insert", id.index_value(), element)));
+
+        let removed = events.clone();
+        let remove_listener = vec.on_remove(move |id, &element| removed.borrow_mut().push(("remove", id.index_value(), element)));
+
+        let a = vec.insert(10);
+        vec.insert(11);
+        vec.insert(12);
+        assert_eq!(vec.remove(a), Some(10));
+        vec.retain(|_, &element| element != 12);
+
+        assert_eq!(*events.borrow(), vec![
+            ("insert", 0, 10), ("insert", 1, 11), ("insert", 2, 12),
+            ("remove", 0, 10), ("remove", 2, 12),
+        ]);
+
+        assert!(vec.remove_listener(remove_listener));
+        assert!(!vec.remove_listener(remove_listener));
+        vec.clear();
+        assert_eq!(events.borrow().len(), 5);
+    }
+
+    #[test]
+    pub fn test_move_listener(){
+        let moves = Rc::new(RefCell::new(Vec::new()));
+        let mut vec = ObservedIdVec::from_id_vec(id_vec!(0, 1, 2));
+
+        let moved = moves.clone();
+        vec.on_move(move |old_id, new_id| moved.borrow_mut().push((old_id, new_id)));
+
+        vec.remove(Id::from_index(0));
+        vec.pack();
+
+        assert_eq!(*moves.borrow(), vec![ (Id::from_index(2), Id::from_index(0)) ]);
+        assert_eq!(vec[Id::from_index(0)], 2);
+    }
+}
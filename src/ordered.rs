@@ -0,0 +1,444 @@
+//! An `IdVec` ordered by a key, backed by a treap of keys whose nodes count the nodes below them.
+//! Inserting, removing, finding the element with a rank, and counting the elements below a key
+//! take logarithmic time in expectation, also when many elements share a key.
+//! Iterating yields each element in logarithmic time, as it is found by its rank.
+
+use ::std::borrow::Borrow;
+use ::std::cmp::Ordering;
+use ::std::collections::HashMap;
+use ::std::ops::{ Deref, Range, RangeBounds, Bound };
+use ::id::*;
+use ::vec::IdVec;
+use ::keyed::{ KeyedIdVec, KeyIndex, KeyGuard };
+
+
+/// An `IdVec` which keeps its elements sorted by a key derived from each element,
+/// enabling range queries, and finding the smallest or largest element in logarithmic time.
+/// Elements with equal keys are ordered by insertion.
+/// Example: a queue of events, ordered by their scheduled time.
+/// Read access to the elements is provided by dereferencing to the `IdVec`.
+pub struct OrderedIdVec<T, K> {
+    keyed: KeyedIdVec<T, K, Order<T, K>>,
+}
+
+/// The ids of all elements ordered by key, and then by insertion sequence.
+/// Stored in a treap, a search tree whose nodes also form a heap of pseudo-random priorities,
+/// which keeps the tree balanced in expectation.
+struct Order<T, K> {
+    nodes: IdVec<Node<T, K>>,
+    root: Link<T, K>,
+
+    /// The insertion sequence of each id index, to find its node when removing it
+    sequences: HashMap<Index, u64>,
+
+    next_sequence: u64,
+}
+
+type Link<T, K> = Option<Id<Node<T, K>>>;
+
+struct Node<T, K> {
+    key: K,
+
+    /// Orders the elements with equal keys
+    sequence: u64,
+
+    id: Id<T>,
+    priority: u64,
+
+    /// The number of nodes in this subtree, including this node
+    size: usize,
+
+    left: Link<T, K>,
+    right: Link<T, K>,
+}
+
+
+/// A pseudo-random priority for each sequence, using the SplitMix64 finalizer
+fn priority(sequence: u64) -> u64 {
+    let mut bits = sequence.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    bits = (bits ^ (bits >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    bits = (bits ^ (bits >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    bits ^ (bits >> 31)
+}
+
+
+impl<T, K> OrderedIdVec<T, K> where K: Ord {
+
+    /// Create a vector ordered by the specified key.
+    /// Example: `OrderedIdVec::new(|event: &Event| event.time)`
+    pub fn new(key: fn(&T) -> K) -> Self {
+        Self::from_id_vec(IdVec::new(), key)
+    }
+
+    /// Order all elements of an existing IdVec. Elements with equal keys are ordered by id.
+    pub fn from_id_vec(elements: IdVec<T>, key: fn(&T) -> K) -> Self {
+        let order = Order { nodes: IdVec::new(), root: None, sequences: HashMap::new(), next_sequence: 0 };
+        OrderedIdVec { keyed: KeyedIdVec::from_id_vec(elements, order, key) }
+    }
+
+    /// Read-only access to the current state
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.keyed.elements
+    }
+
+    /// Drop the ordering
+    pub fn into_id_vec(self) -> IdVec<T> {
+        self.keyed.elements
+    }
+
+    /// The elements with these ranks, ordered by key
+    fn ranks(&self, ranks: Range<usize>) -> impl DoubleEndedIterator<Item = (Id<T>, &T)> + '_ {
+        ranks.map(move |rank| self.by_rank(rank).expect("rank out of range"))
+    }
+
+    /// The number of elements whose key is smaller than the specified key, or also equal if `or_equal`
+    fn count_up_to<Q>(&self, key: &Q, or_equal: bool) -> usize where K: Borrow<Q>, Q: Ord + ?Sized {
+        self.keyed.index.count(|element_key, _| match Borrow::<Q>::borrow(element_key).cmp(key) {
+            Ordering::Less => true,
+            Ordering::Equal => or_equal,
+            Ordering::Greater => false,
+        })
+    }
+
+
+
+    /// Return a guard which allows modifying the element that this id points to.
+    /// The element is moved to its new position when the guard is dropped.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<KeyGuard<'_, T, K>> {
+        self.keyed.get_mut(id)
+    }
+
+    /// Associate the specified element with a currently unused id,
+    /// placing it after all elements with an equal key
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        self.keyed.insert(element)
+    }
+
+    /// Remove the element and its key from the ordering.
+    /// Ignores invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        self.keyed.remove(id)
+    }
+
+    /// Remove all elements and keys, keeping the allocated memory
+    pub fn clear(&mut self) {
+        self.keyed.clear();
+    }
+
+
+
+    /// All ids and elements whose key lies inside the range, ordered by key.
+    /// Example: `events.range(start_time .. end_time)`
+    pub fn range<Q, R>(&self, range: R) -> impl DoubleEndedIterator<Item = (Id<T>, &T)> + '_
+        where K: Borrow<Q>, R: RangeBounds<Q>, Q: Ord + ?Sized
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.count_up_to(key, false),
+            Bound::Excluded(key) => self.count_up_to(key, true),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.count_up_to(key, true),
+            Bound::Excluded(key) => self.count_up_to(key, false),
+            Bound::Unbounded => self.keyed.index.len(),
+        };
+
+        self.ranks(start .. end.max(start))
+    }
+
+    /// All ids and elements, ordered by key
+    pub fn iter_ordered(&self) -> impl DoubleEndedIterator<Item = (Id<T>, &T)> + '_ {
+        self.ranks(0 .. self.keyed.index.len())
+    }
+
+    /// The element with the smallest key, the first inserted one if there are multiple
+    pub fn min(&self) -> Option<(Id<T>, &T)> {
+        self.iter_ordered().next()
+    }
+
+    /// The element with the largest key, the last inserted one if there are multiple
+    pub fn max(&self) -> Option<(Id<T>, &T)> {
+        self.iter_ordered().next_back()
+    }
+
+    /// Remove the element with the smallest key, the first inserted one if there are multiple.
+    /// Complexity of O(log n).
+    pub fn pop_min(&mut self) -> Option<(Id<T>, T)> {
+        let (id, _) = self.min()?;
+        self.remove(id).map(|element| (id, element))
+    }
+
+    /// The number of elements ordered before the element that this id points to.
+    /// Complexity of O(log n).
+    pub fn rank(&self, id: Id<T>) -> Option<usize> {
+        let key = (self.keyed.key)(self.get(id)?);
+        let sequence = self.keyed.index.sequences[&id.index_value()];
+        Some(self.keyed.index.count(|element_key, element_sequence| (element_key, element_sequence) < (&key, sequence)))
+    }
+
+    /// The element with the specified rank, such that `rank(by_rank(n).0) == Some(n)`.
+    /// Complexity of O(log n).
+    pub fn by_rank(&self, rank: usize) -> Option<(Id<T>, &T)> {
+        self.keyed.index.nth(rank).map(|id| (id, &self[id]))
+    }
+
+    /// The number of elements whose key is smaller than the specified key.
+    /// Complexity of O(log n).
+    pub fn count_below<Q>(&self, key: &Q) -> usize where K: Borrow<Q>, Q: Ord + ?Sized {
+        self.count_up_to(key, false)
+    }
+}
+
+impl<T, K> Deref for OrderedIdVec<T, K> {
+    type Target = IdVec<T>;
+    fn deref(&self) -> &IdVec<T> {
+        &self.keyed.elements
+    }
+}
+
+impl<T, K> Order<T, K> {
+    fn size(&self, link: Link<T, K>) -> usize {
+        link.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    fn update_size(&mut self, node: Id<Node<T, K>>) {
+        self.nodes[node].size = 1 + self.size(self.nodes[node].left) + self.size(self.nodes[node].right);
+    }
+
+    /// Join two trees, where all nodes of the left tree are ordered before the nodes of the right tree
+    fn merge(&mut self, left: Link<T, K>, right: Link<T, K>) -> Link<T, K> {
+        match (left, right) {
+            (None, tree) | (tree, None) => tree,
+
+            (Some(left), Some(right)) => {
+                if self.nodes[left].priority > self.nodes[right].priority {
+                    let merged = self.merge(self.nodes[left].right, Some(right));
+                    self.nodes[left].right = merged;
+                    self.update_size(left);
+                    Some(left)
+
+                } else {
+                    let merged = self.merge(Some(left), self.nodes[right].left);
+                    self.nodes[right].left = merged;
+                    self.update_size(right);
+                    Some(right)
+                }
+            }
+        }
+    }
+
+    /// Split a tree into the nodes for which `is_before` returns true, and all following nodes.
+    /// `is_before` must return true for a prefix of the order.
+    fn split<F>(&mut self, tree: Link<T, K>, is_before: &F) -> (Link<T, K>, Link<T, K>) where F: Fn(&K, u64) -> bool {
+        let node = match tree {
+            Some(node) => node,
+            None => return (None, None),
+        };
+
+        if is_before(&self.nodes[node].key, self.nodes[node].sequence) {
+            let (before, after) = self.split(self.nodes[node].right, is_before);
+            self.nodes[node].right = before;
+            self.update_size(node);
+            (Some(node), after)
+
+        } else {
+            let (before, after) = self.split(self.nodes[node].left, is_before);
+            self.nodes[node].left = after;
+            self.update_size(node);
+            (before, Some(node))
+        }
+    }
+
+    /// The number of nodes for which `is_before` returns true, which must be a prefix of the order
+    fn count<F>(&self, is_before: F) -> usize where F: Fn(&K, u64) -> bool {
+        let mut count = 0;
+        let mut link = self.root;
+
+        while let Some(node) = link {
+            let node = &self.nodes[node];
+
+            if is_before(&node.key, node.sequence) {
+                count += self.size(node.left) + 1;
+                link = node.right;
+            } else {
+                link = node.left;
+            }
+        }
+
+        count
+    }
+
+    /// The id of the element with this rank
+    fn nth(&self, mut rank: usize) -> Option<Id<T>> {
+        let mut link = self.root;
+
+        while let Some(node) = link {
+            let node = &self.nodes[node];
+            let left_size = self.size(node.left);
+
+            match rank.cmp(&left_size) {
+                Ordering::Less => link = node.left,
+                Ordering::Equal => return Some(node.id),
+                Ordering::Greater => {
+                    rank -= left_size + 1;
+                    link = node.right;
+                },
+            }
+        }
+
+        None
+    }
+}
+
+impl<T, K> KeyIndex<T, K> for Order<T, K> where K: Ord {
+    fn add(&mut self, key: K, id: Id<T>) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.sequences.insert(id.index_value(), sequence);
+
+        let (before, after) = self.split(self.root, &|node_key: &K, node_sequence| (node_key, node_sequence) < (&key, sequence));
+
+        let node = self.nodes.insert(Node { key, sequence, id, priority: priority(sequence), size: 1, left: None, right: None });
+        let before = self.merge(before, Some(node));
+        self.root = self.merge(before, after);
+    }
+
+    fn remove(&mut self, key: &K, id: Id<T>) {
+        let sequence = match self.sequences.remove(&id.index_value()) {
+            Some(sequence) => sequence,
+            None => return,
+        };
+
+        let (before, rest) = self.split(self.root, &|node_key: &K, node_sequence| (node_key, node_sequence) < (key, sequence));
+        let (removed, after) = self.split(rest, &|node_key: &K, node_sequence| (node_key, node_sequence) <= (key, sequence));
+
+        if let Some(removed) = removed {
+            self.nodes.remove(removed);
+        }
+
+        self.root = self.merge(before, after);
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+        self.sequences.clear();
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Event { time: u32, name: &'static str }
+
+    fn event(time: u32, name: &'static str) -> Event {
+        Event { time, name }
+    }
+
+    fn names<'s, I: Iterator<Item = (Id<Event>, &'s Event)>>(iter: I) -> Vec<&'static str> {
+        iter.map(|(_, event)| event.name).collect()
+    }
+
+    #[test]
+    pub fn test_range_min_max(){
+        let mut events = OrderedIdVec::new(|event: &Event| event.time);
+        events.insert(event(30, "c"));
+        let a = events.insert(event(10, "a"));
+        events.insert(event(20, "b1"));
+        events.insert(event(20, "b2"));
+        let d = events.insert(event(40, "d"));
+
+        assert_eq!(names(events.iter_ordered()), vec!["a", "b1", "b2", "c", "d"]);
+        assert_eq!(names(events.range(15 .. 40)), vec!["b1", "b2", "c"]);
+        assert_eq!(names(events.range(20 ..= 40).rev()), vec!["d", "c", "b2", "b1"]);
+        assert_eq!(events.min(), Some((a, &event(10, "a"))));
+        assert_eq!(events.max().map(|(id, _)| id), Some(d));
+
+        assert_eq!(events.pop_min(), Some((a, event(10, "a"))));
+        assert_eq!(events.min().unwrap().1.name, "b1");
+        assert_eq!(events.len(), 4);
+    }
+
+    #[test]
+    pub fn test_many_equal_keys(){
+        let mut queue = OrderedIdVec::new(|&(time, _): &(u32, usize)| time);
+        let ids: Vec<_> = (0 .. 1000).map(|index| queue.insert((7, index))).collect();
+
+        queue.remove(ids[500]);
+        assert_eq!(queue.rank(ids[501]), Some(500));
+
+        queue.get_mut(ids[0]).unwrap().0 = 8;
+        assert_eq!(queue.max().map(|(id, _)| id), Some(ids[0]), "moved behind all other elements");
+
+        let drained: Vec<usize> = ::std::iter::from_fn(|| queue.pop_min()).map(|(_, (_, index))| index).collect();
+        let expected: Vec<usize> = (1 .. 1000).filter(|&index| index != 500).chain(Some(0)).collect();
+        assert_eq!(drained, expected, "equal keys in insertion order");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    pub fn test_rank_and_guarded_modification(){
+        let mut events = OrderedIdVec::new(|event: &Event| event.time);
+        let first = events.insert(event(1, "first"));
+        let second = events.insert(event(2, "second"));
+        let third = events.insert(event(3, "third"));
+
+        assert_eq!(events.rank(third), Some(2));
+        assert_eq!(events.count_below(&2), 1);
+
+        events.get_mut(first).unwrap().time = 5;
+        assert_eq!(names(events.iter_ordered()), vec!["second", "third", "first"]);
+        assert_eq!(events.rank(first), Some(2));
+        assert_eq!(events.by_rank(0).map(|(id, _)| id), Some(second));
+        assert_eq!(events.by_rank(3), None);
+
+        events.get_mut(third).unwrap().name = "renamed";
+        assert_eq!(events.rank(third), Some(1), "unchanged key keeps position");
+
+        events.remove(second);
+        assert_eq!(events.rank(second), None);
+        assert_eq!(names(events.range(.. 10)), vec!["renamed", "first"]);
+        assert!(events.range(6 ..).next().is_none());
+    }
+
+    #[test]
+    pub fn test_ranks_match_sorted_elements(){
+        let mut ordered = OrderedIdVec::new(|&(key, _): &(u32, u32)| key);
+        let mut ids = Vec::new();
+        let mut random = 12345_u32;
+
+        for index in 0 .. 2000 {
+            random = random.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ids.push(ordered.insert(((random >> 16) % 100, index)));
+
+            if random.is_multiple_of(3) {
+                let removed = ids.swap_remove((random >> 8) as usize % ids.len());
+                ordered.remove(removed);
+            }
+        }
+
+        // the elements sorted by key, and then by insertion, which is the order of the index here
+        let mut sorted: Vec<_> = ordered.iter().map(|(id, &element)| (id, element)).collect();
+        sorted.sort_by_key(|&(_, (key, index))| (key, index));
+
+        for (rank, &(id, element)) in sorted.iter().enumerate() {
+            assert_eq!(ordered.rank(id), Some(rank));
+            assert_eq!(ordered.by_rank(rank), Some((id, &element)));
+        }
+
+        assert_eq!(ordered.by_rank(sorted.len()), None);
+        assert_eq!(ordered.count_below(&50), sorted.iter().filter(|&&(_, (key, _))| key < 50).count());
+        assert_eq!(ordered.range(20 .. 30).count(), sorted.iter().filter(|&&(_, (key, _))| (20 .. 30).contains(&key)).count());
+        assert_eq!(ordered.iter_ordered().rev().map(|(id, _)| id).collect::<Vec<_>>(),
+                   sorted.iter().rev().map(|&(id, _)| id).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,153 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// The differences between two `IdVec`s, as returned by `IdVec::diff`.
+/// Applying the patch to the old vector reproduces the new vector with identical ids.
+/// All entries are sorted by id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdVecPatch<T> {
+    /// Elements which only exist in the new vector
+    pub inserted: Vec<(Id<T>, T)>,
+
+    /// Ids which only exist in the old vector
+    pub removed: Vec<Id<T>>,
+
+    /// Elements which exist in both vectors, but are not equal, with their new value
+    pub changed: Vec<(Id<T>, T)>,
+}
+
+
+impl<T> IdVecPatch<T> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        IdVecPatch { inserted: Vec::new(), removed: Vec::new(), changed: Vec::new() }
+    }
+
+    /// Returns if both vectors were equal
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The total number of inserted, removed and changed entries
+    pub fn len(&self) -> usize {
+        self.inserted.len() + self.removed.len() + self.changed.len()
+    }
+}
+
+
+impl<T> IdVec<T> {
+
+    /// Compare the ids and elements of both vectors, returning the patch that transforms `old` into `new`.
+    /// Complexity of O(n), where n is the larger id index limit.
+    pub fn diff(old: &IdVec<T>, new: &IdVec<T>) -> IdVecPatch<T> where T: Clone + PartialEq {
+        let mut patch = IdVecPatch::new();
+        let limit = old.id_index_limit().max(new.id_index_limit());
+
+        for index in 0 .. limit {
+            let id = Id::from_index(index);
+
+            match (old.get(id), new.get(id)) {
+                (None, Some(element)) => patch.inserted.push((id, element.clone())),
+                (Some(_), None) => patch.removed.push(id),
+
+                (Some(old_element), Some(new_element)) => if old_element != new_element {
+                    patch.changed.push((id, new_element.clone()))
+                },
+
+                (None, None) => {},
+            }
+        }
+
+        patch
+    }
+
+    /// Apply the patch that was created by comparing this vector to another vector.
+    /// Afterwards, this vector contains the same ids and elements as the other vector.
+    /// Panics in debug builds if the patch was not created from this vector.
+    pub fn apply_patch(&mut self, patch: IdVecPatch<T>) {
+        // remove first, so that the unused slots at the back can be deallocated
+        for id in patch.removed {
+            let removed = self.remove(id);
+            debug_assert!(removed.is_some(), "Patch removes missing element {:?}", id);
+        }
+
+        for (id, element) in patch.changed {
+            let previous = self.insert_at(id, element);
+            debug_assert!(previous.is_some(), "Patch changes missing element {:?}", id);
+        }
+
+        for (id, element) in patch.inserted {
+            let previous = self.insert_at(id, element);
+            debug_assert!(previous.is_none(), "Patch inserts into existing element {:?}", id);
+        }
+    }
+}
+
+
+impl<T> Default for IdVecPatch<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lists one entry per line, prefixed with `+`, `-` or `~`
+impl<T> ::std::fmt::Display for IdVecPatch<T> where T: ::std::fmt::Debug {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        for (id, element) in &self.inserted {
+            writeln!(f, "+ {:?}: {:?}", id, element)?;
+        }
+
+        for id in &self.removed {
+            writeln!(f, "- {:?}", id)?;
+        }
+
+        for (id, element) in &self.changed {
+            writeln!(f, "~ {:?}: {:?}", id, element)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_diff(){
+        let old = id_vec!("a", "b", "c");
+        let mut new = old.clone();
+        new.remove(Id::from_index(1));
+        new[Id::from_index(2)] = "x";
+        new.insert_at(Id::from_index(4), "y");
+
+        let patch = IdVec::diff(&old, &new);
+        assert_eq!(patch.inserted, vec![ (Id::from_index(4), "y") ]);
+        assert_eq!(patch.removed, vec![ Id::from_index(1) ]);
+        assert_eq!(patch.changed, vec![ (Id::from_index(2), "x") ]);
+        assert_eq!(patch.to_string(), "+ Id#4: \"y\"\n- Id#1\n~ Id#2: \"x\"\n");
+
+        assert!(IdVec::diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    pub fn test_apply_patch_keeps_ids(){
+        let mut old = id_vec!(0, 1, 2, 3, 4);
+        old.remove(Id::from_index(1));
+
+        let mut new = id_vec!(0, 1, 20);
+        new.remove(Id::from_index(0));
+        new.insert_at(Id::from_index(6), 6);
+
+        let patch = IdVec::diff(&old, &new);
+        old.apply_patch(patch);
+        assert_eq!(old, new);
+        assert_eq!(old.id_index_limit(), new.id_index_limit(), "unused slots are identical");
+        assert_eq!(old.len(), 3);
+    }
+}
@@ -0,0 +1,256 @@
+
+use ::std::collections::BinaryHeap;
+use ::std::cmp::Ordering;
+use ::std::ops::Add;
+use ::id::*;
+use ::read::ReadIdVec;
+use ::table::IdTable;
+use ::bits::BitSet;
+use ::traverse::Neighbors;
+
+
+/// The result of a shortest path search: For every reached element,
+/// the total cost from the start element, and the previous element on the cheapest path.
+pub struct ShortestPaths<T, C> {
+    start: Id<T>,
+    distances: IdTable<T, C>,
+    predecessors: IdTable<T, Id<T>>,
+}
+
+impl<T, C> ShortestPaths<T, C> {
+
+    pub fn start(&self) -> Id<T> {
+        self.start
+    }
+
+    /// The total cost of the cheapest path from the start element.
+    /// Returns `None` if the element has not been reached.
+    pub fn distance(&self, id: Id<T>) -> Option<&C> {
+        self.distances.get(id)
+    }
+
+    /// The previous element on the cheapest path from the start element.
+    /// Returns `None` for the start element itself, and for elements that have not been reached.
+    pub fn predecessor(&self, id: Id<T>) -> Option<Id<T>> {
+        self.predecessors.get(id).cloned()
+    }
+
+    /// The cheapest path from the start element to the target element, including both.
+    /// Returns `None` if the target has not been reached.
+    pub fn path_to(&self, target: Id<T>) -> Option<Vec<Id<T>>> {
+        if !self.distances.contains_id(target) {
+            return None;
+        }
+
+        let mut path = vec![ target ];
+        while let Some(predecessor) = self.predecessor(*path.last().unwrap()) {
+            path.push(predecessor);
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    /// The total costs of all reached elements
+    pub fn distances(&self) -> &IdTable<T, C> {
+        &self.distances
+    }
+
+    /// The previous elements of all reached elements, except for the start element
+    pub fn predecessors(&self) -> &IdTable<T, Id<T>> {
+        &self.predecessors
+    }
+}
+
+
+
+/// An element in the priority queue, ordered by lowest estimated cost first.
+/// Incomparable costs like `NaN` are considered equal.
+struct Estimate<C> {
+    cost: C,
+    index: Index,
+}
+
+impl<C: PartialOrd> Ord for Estimate<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal) // reversed for a min-heap
+    }
+}
+
+impl<C: PartialOrd> PartialOrd for Estimate<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: PartialOrd> Eq for Estimate<C> {}
+impl<C: PartialOrd> PartialEq for Estimate<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+
+
+/// Find the cheapest paths from the start element to all reachable elements.
+/// The cost of following a reference is computed by `cost(from, to)` and must not be negative.
+/// `C::default()` is used as the cost of the start element.
+pub fn dijkstra<T, V, C, F>(vec: &V, start: Id<T>, cost: F) -> ShortestPaths<T, C>
+    where T: Neighbors, V: ReadIdVec<T>, C: Copy + PartialOrd + Add<Output = C> + Default, F: FnMut(Id<T>, Id<T>) -> C
+{
+    search(vec, start, None, cost, |_| C::default())
+}
+
+/// Find the cheapest path from the start element to the goal element,
+/// visiting elements with the lowest estimated total cost first.
+/// The `heuristic(id)` estimates the remaining cost to the goal. It must be consistent:
+/// it must be zero at the goal, and must never decrease by more than the cost of a step,
+/// `heuristic(from) <= cost(from, to) + heuristic(to)`. Never overestimating is not enough,
+/// because elements are not visited again once their path has been finalized.
+/// Stops as soon as the goal is reached, so only the path to the goal is guaranteed to be the cheapest.
+pub fn astar<T, V, C, F, H>(vec: &V, start: Id<T>, goal: Id<T>, cost: F, heuristic: H) -> ShortestPaths<T, C>
+    where T: Neighbors, V: ReadIdVec<T>, C: Copy + PartialOrd + Add<Output = C> + Default,
+          F: FnMut(Id<T>, Id<T>) -> C, H: FnMut(Id<T>) -> C
+{
+    search(vec, start, Some(goal), cost, heuristic)
+}
+
+fn search<T, V, C, F, H>(vec: &V, start: Id<T>, goal: Option<Id<T>>, mut cost: F, mut heuristic: H) -> ShortestPaths<T, C>
+    where T: Neighbors, V: ReadIdVec<T>, C: Copy + PartialOrd + Add<Output = C> + Default,
+          F: FnMut(Id<T>, Id<T>) -> C, H: FnMut(Id<T>) -> C
+{
+    let mut paths = ShortestPaths {
+        start,
+        distances: IdTable::with_id_index_limit(vec.id_index_limit()),
+        predecessors: IdTable::with_id_index_limit(vec.id_index_limit()),
+    };
+
+    if !vec.contains_id(start) {
+        return paths;
+    }
+
+    let mut finished = BitSet::new(vec.id_index_limit());
+    let mut queue = BinaryHeap::new();
+
+    paths.distances.insert(start, C::default());
+    queue.push(Estimate { cost: heuristic(start), index: start.index_value() });
+
+    while let Some(Estimate { index, .. }) = queue.pop() {
+        // an element may be queued multiple times, but only the cheapest one is finished
+        if !finished.insert(index) {
+            continue;
+        }
+
+        let from = Id::from_index(index);
+        if Some(from) == goal {
+            break;
+        }
+
+        let distance = paths.distances[from];
+        vec.get(from).expect("finished element not found").visit_neighbors(|to| {
+            if !vec.contains_id(to) || finished.contains(to.index_value()) {
+                return;
+            }
+
+            let new_distance = distance + cost(from, to);
+            let is_cheaper = paths.distances.get(to).is_none_or(|&old_distance| new_distance < old_distance);
+
+            if is_cheaper {
+                paths.distances.insert(to, new_distance);
+                paths.predecessors.insert(to, from);
+                queue.push(Estimate { cost: new_distance + heuristic(to), index: to.index_value() });
+            }
+        });
+    }
+
+    paths
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::vec::IdVec;
+
+    struct Waypoint {
+        position: (f32, f32),
+        connections: Vec<Id<Waypoint>>,
+    }
+
+    impl Neighbors for Waypoint {
+        fn visit_neighbors<F: FnMut(Id<Self>)>(&self, visit: F) {
+            self.connections.iter().cloned().for_each(visit)
+        }
+    }
+
+    fn waypoint(x: f32, y: f32, connections: &[Index]) -> Waypoint {
+        Waypoint {
+            position: (x, y),
+            connections: connections.iter().map(|&index| Id::from_index(index)).collect()
+        }
+    }
+
+    fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    /// 0 -> 1 -> 3 is a detour, 0 -> 2 -> 3 is shorter, 4 is unreachable
+    fn map() -> IdVec<Waypoint> {
+        id_vec!(
+            waypoint(0.0, 0.0, &[1, 2]),
+            waypoint(0.0, 5.0, &[3]),
+            waypoint(2.0, 1.0, &[3]),
+            waypoint(4.0, 0.0, &[]),
+            waypoint(9.0, 9.0, &[0])
+        )
+    }
+
+    #[test]
+    pub fn test_dijkstra(){
+        let waypoints = map();
+        let paths = dijkstra(&waypoints, Id::from_index(0), |from, to| {
+            distance(waypoints[from].position, waypoints[to].position)
+        });
+
+        assert_eq!(paths.distance(Id::from_index(0)), Some(&0.0));
+        assert_eq!(paths.distance(Id::from_index(1)), Some(&5.0));
+        assert_eq!(paths.distance(Id::from_index(4)), None, "unreachable");
+        assert_eq!(paths.predecessor(Id::from_index(3)), Some(Id::from_index(2)));
+        assert_eq!(paths.predecessor(Id::from_index(0)), None);
+        assert_eq!(paths.distances().len(), 4);
+
+        assert_eq!(
+            paths.path_to(Id::from_index(3)),
+            Some(vec![ Id::from_index(0), Id::from_index(2), Id::from_index(3) ])
+        );
+    }
+
+    #[test]
+    pub fn test_astar(){
+        let waypoints = map();
+        let goal = Id::from_index(3);
+
+        let paths = astar(
+            &waypoints, Id::from_index(4), goal,
+            |from, to| distance(waypoints[from].position, waypoints[to].position),
+            |id| distance(waypoints[id].position, waypoints[goal].position)
+        );
+
+        assert_eq!(
+            paths.path_to(goal),
+            Some(vec![ Id::from_index(4), Id::from_index(0), Id::from_index(2), Id::from_index(3) ])
+        );
+
+        let expected = distance((9.0, 9.0), (0.0, 0.0)) + distance((0.0, 0.0), (2.0, 1.0)) + distance((2.0, 1.0), (4.0, 0.0));
+        assert!((paths.distance(goal).unwrap() - expected).abs() < 0.0001);
+    }
+
+    #[test]
+    pub fn test_integer_costs(){
+        let waypoints = map();
+        let paths = dijkstra(&waypoints, Id::from_index(0), |_, _| 1_u32);
+        assert_eq!(paths.distance(Id::from_index(3)), Some(&2));
+        assert_eq!(paths.path_to(Id::from_index(4)), None);
+    }
+}
@@ -0,0 +1,256 @@
+
+use ::std::rc::{ Rc, Weak };
+use ::std::cell::RefCell;
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Indices of elements whose last handle has been dropped
+type DroppedIndices = Rc<RefCell<Vec<Index>>>;
+
+
+/// Marks the element as dropped when the last handle to it is dropped
+struct HandleTarget<T> {
+    id: Id<T>,
+    dropped: DroppedIndices,
+}
+
+impl<T> Drop for HandleTarget<T> {
+    fn drop(&mut self) {
+        self.dropped.borrow_mut().push(self.id.index_value());
+    }
+}
+
+
+/// A reference-counted id. The element stays inside the `RcIdVec` as long as any handle to it exists.
+/// Cloning a handle increments the reference count.
+pub struct Handle<T> {
+    target: Rc<HandleTarget<T>>,
+}
+
+/// A non-owning id, which does not prevent the element from being removed.
+/// Can be upgraded to a `Handle` while the element still exists.
+pub struct WeakId<T> {
+    target: Weak<HandleTarget<T>>,
+}
+
+
+/// An `IdVec` whose elements are removed automatically when the last handle to them is dropped.
+/// To avoid shared mutable access, removal is deferred until `maintain()` is called.
+/// Handles must only be used with the `RcIdVec` that created them.
+pub struct RcIdVec<T> {
+    elements: IdVec<T>,
+    dropped: DroppedIndices,
+}
+
+
+impl<T> RcIdVec<T> {
+
+    /// Does not allocate heap memory for elements
+    pub fn new() -> Self {
+        RcIdVec { elements: IdVec::new(), dropped: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    /// The number of elements, including elements without handles that have not been maintained yet
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Read-only access to the underlying IdVec,
+    /// including elements without handles that have not been maintained yet
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        &self.elements
+    }
+
+    /// Insert the element, returning the first handle to it
+    pub fn insert(&mut self, element: T) -> Handle<T> {
+        let id = self.elements.insert(element);
+        Handle { target: Rc::new(HandleTarget { id, dropped: self.dropped.clone() }) }
+    }
+
+    /// The element is guaranteed to exist as long as the handle exists
+    pub fn get(&self, handle: &Handle<T>) -> &T {
+        &self.elements[handle.id()]
+    }
+
+    /// The element is guaranteed to exist as long as the handle exists
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> &mut T {
+        &mut self.elements[handle.id()]
+    }
+
+    /// Returns `None` if the element has no handles left
+    pub fn get_weak(&self, weak: &WeakId<T>) -> Option<&T> {
+        weak.upgrade().map(|handle| self.get(&handle))
+    }
+
+    /// Remove all elements whose last handle has been dropped.
+    /// Handles stored inside removed elements are dropped as well,
+    /// which may cause further elements to be removed.
+    /// Returns the number of removed elements.
+    pub fn maintain(&mut self) -> usize {
+        let mut removed = 0;
+
+        loop {
+            // release the borrow before dropping elements, which may drop further handles
+            let dropped = ::std::mem::take(&mut *self.dropped.borrow_mut());
+            if dropped.is_empty() {
+                return removed;
+            }
+
+            for index in dropped {
+                self.elements.remove(Id::from_index(index));
+                removed += 1;
+            }
+        }
+    }
+
+    /// Used for immutable access to ids and elements,
+    /// including elements without handles that have not been maintained yet
+    pub fn iter(&self) -> ::vec::Iter<'_, T> {
+        self.elements.iter()
+    }
+}
+
+impl<T> Default for RcIdVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'h, T> ::std::ops::Index<&'h Handle<T>> for RcIdVec<T> {
+    type Output = T;
+    fn index(&self, handle: &'h Handle<T>) -> &T {
+        self.get(handle)
+    }
+}
+
+impl<'h, T> ::std::ops::IndexMut<&'h Handle<T>> for RcIdVec<T> {
+    fn index_mut(&mut self, handle: &'h Handle<T>) -> &mut T {
+        self.get_mut(handle)
+    }
+}
+
+
+
+impl<T> Handle<T> {
+
+    /// The plain id of the element, which is only valid as long as any handle exists
+    pub fn id(&self) -> Id<T> {
+        self.target.id
+    }
+
+    /// Create a non-owning id to the same element
+    pub fn downgrade(&self) -> WeakId<T> {
+        WeakId { target: Rc::downgrade(&self.target) }
+    }
+
+    /// The number of handles to the element, including this one
+    pub fn handle_count(&self) -> usize {
+        Rc::strong_count(&self.target)
+    }
+}
+
+impl<T> WeakId<T> {
+
+    /// Returns `None` if the last handle has already been dropped
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        self.target.upgrade().map(|target| Handle { target })
+    }
+}
+
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle { target: self.target.clone() }
+    }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.target, &other.target)
+    }
+}
+impl<T> ::std::hash::Hash for Handle<T> {
+    fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
+    }
+}
+impl<T> ::std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "Handle#{:?}", self.id().index_value())
+    }
+}
+
+impl<T> Clone for WeakId<T> {
+    fn clone(&self) -> Self {
+        WeakId { target: self.target.clone() }
+    }
+}
+impl<T> ::std::fmt::Debug for WeakId<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match self.upgrade() {
+            Some(handle) => write!(f, "WeakId#{:?}", handle.id().index_value()),
+            None => write!(f, "WeakId#dropped"),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_remove_on_maintain(){
+        let mut vec = RcIdVec::new();
+        let a = vec.insert("a");
+        let b = vec.insert("b");
+        let a2 = a.clone();
+        assert_eq!(a.handle_count(), 2);
+        assert_eq!(vec[&a2], "a");
+
+        drop(a);
+        assert_eq!(vec.maintain(), 0, "element still has a handle");
+
+        drop(a2);
+        assert_eq!(vec.len(), 2, "removal is deferred");
+        assert_eq!(vec.maintain(), 1);
+        assert_eq!(vec.len(), 1);
+        assert_eq!(vec.get(&b), &"b");
+    }
+
+    #[test]
+    pub fn test_weak_ids(){
+        let mut vec = RcIdVec::new();
+        let a = vec.insert(1);
+        let weak = a.downgrade();
+        assert_eq!(weak.upgrade(), Some(a.clone()));
+        assert_eq!(vec.get_weak(&weak), Some(&1));
+
+        drop(a);
+        vec.maintain();
+        assert_eq!(weak.upgrade(), None);
+
+        // the slot is reused, but the weak id does not point to the new element
+        let _b = vec.insert(2);
+        assert_eq!(vec.get_weak(&weak), None);
+    }
+
+    #[test]
+    pub fn test_cascading_removal(){
+        struct Node { _child: Option<Handle<Node>> }
+
+        let mut vec = RcIdVec::new();
+        let child = vec.insert(Node { _child: None });
+        let parent = vec.insert(Node { _child: Some(child) });
+
+        drop(parent);
+        assert_eq!(vec.maintain(), 2, "dropping the parent drops the handle to the child");
+        assert!(vec.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Read-only access to elements by their ids, implemented by `IdVec` and `MmapIdVec`.
+/// The graph algorithms of this crate accept any implementation,
+/// so that they can run directly on a memory-mapped file, without copying it first.
+pub trait ReadIdVec<T> {
+
+    /// Return a reference to the element that this id points to
+    fn get(&self, id: Id<T>) -> Option<&T>;
+
+    /// Excludes deleted elements, and indices out of range
+    fn contains_id(&self, id: Id<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// All ids and elements, in the order of their ids
+    fn iter<'s>(&'s self) -> impl Iterator<Item = (Id<T>, &'s T)> + 's where T: 's;
+
+    /// The number of used ids
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// No id has an index value greater than or equal to this value
+    fn id_index_limit(&self) -> usize;
+}
+
+
+impl<T> ReadIdVec<T> for IdVec<T> {
+    fn get(&self, id: Id<T>) -> Option<&T> {
+        IdVec::get(self, id)
+    }
+
+    fn contains_id(&self, id: Id<T>) -> bool {
+        IdVec::contains_id(self, id)
+    }
+
+    fn iter<'s>(&'s self) -> impl Iterator<Item = (Id<T>, &'s T)> + 's where T: 's {
+        IdVec::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        IdVec::len(self)
+    }
+
+    fn id_index_limit(&self) -> usize {
+        IdVec::id_index_limit(self)
+    }
+}
@@ -0,0 +1,379 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// What happens to the children when the parent they reference is removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OnDelete {
+    /// Refuse to remove a parent while any child references it
+    Restrict,
+
+    /// Also remove all children referencing the parent
+    Cascade,
+
+    /// Set the reference of all children to `None`
+    SetNone,
+}
+
+
+/// Describes that elements of an `IdVec<C>` reference elements of an `IdVec<P>`
+/// through an `Option<Id<P>>` field, like a foreign key in a database.
+/// Removing parents through the relation keeps the references of the children valid.
+pub struct Relation<P, C> {
+    get: fn(&C) -> Option<Id<P>>,
+    set: fn(&mut C, Option<Id<P>>),
+    policy: OnDelete,
+}
+
+/// Several relations which reference the same parent type, each together with its children.
+/// Removing a parent through all of them at once enforces every policy,
+/// and does not modify anything if any relation restricts the removal.
+/// Example: `Relations::new().with(material_of_mesh, &mut meshes).with(material_of_light, &mut lights)`
+pub struct Relations<'s, P> {
+    links: Vec<Box<dyn Link<P> + 's>>,
+}
+
+
+/// The children modified by `Relation::on_delete`
+pub struct Affected<C> {
+    /// The children removed by `OnDelete::Cascade`
+    pub cascaded: Vec<(Id<C>, C)>,
+
+    /// The children whose reference was set to `None` by `OnDelete::SetNone`
+    pub cleared: Vec<Id<C>>,
+}
+
+/// The elements removed by `Relation::remove`
+pub struct Removal<P, C> {
+    /// The removed parent
+    pub parent: P,
+
+    /// The children modified because of the removed parent
+    pub children: Affected<C>,
+}
+
+
+/// Returned by `OnDelete::Restrict` relations if the parent is still referenced
+// manually implementing clone, debug, eq, because C does not need to implement them
+pub struct Restricted<C> {
+    /// All children that reference the parent
+    pub children: Vec<Id<C>>,
+}
+
+/// Returned by `Relations::remove` if a restricting relation still references the parent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestrictedBy {
+    /// The position of the restricting relation, in the order the relations were added
+    pub relation: usize,
+
+    /// The indices of all children of that relation which reference the parent
+    pub children: Vec<Index>,
+}
+
+
+/// A relation together with its children, hiding the type of the children
+trait Link<P> {
+    fn restricting_children(&self, parent: Id<P>) -> Vec<Index>;
+    fn on_delete(&mut self, parent: Id<P>);
+}
+
+struct BoundRelation<'s, P, C: 's> {
+    relation: Relation<P, C>,
+    children: &'s mut IdVec<C>,
+}
+
+impl<'s, P, C: 's> Link<P> for BoundRelation<'s, P, C> {
+    fn restricting_children(&self, parent: Id<P>) -> Vec<Index> {
+        match self.relation.restrict(self.children, parent) {
+            Ok(()) => Vec::new(),
+            Err(restricted) => restricted.children.iter().map(|child| child.index_value()).collect(),
+        }
+    }
+
+    fn on_delete(&mut self, parent: Id<P>) {
+        self.relation.on_delete(self.children, parent)
+            .expect("restricting relation has been checked before");
+    }
+}
+
+
+impl<P, C> Relation<P, C> {
+
+    /// Create a relation, given functions that read and write the reference field of a child.
+    /// Example: `Relation::new(|child: &Child| child.parent, |child, parent| child.parent = parent, OnDelete::Cascade)`
+    pub fn new(get: fn(&C) -> Option<Id<P>>, set: fn(&mut C, Option<Id<P>>), policy: OnDelete) -> Self {
+        Relation { get, set, policy }
+    }
+
+    pub fn policy(&self) -> OnDelete {
+        self.policy
+    }
+
+    /// Return the parent referenced by the child, if any
+    pub fn parent_of(&self, children: &IdVec<C>, child: Id<C>) -> Option<Id<P>> {
+        children.get(child).and_then(self.get)
+    }
+
+    /// All children referencing the parent.
+    /// Complexity of O(n), where n is the number of children.
+    pub fn children_of(&self, children: &IdVec<C>, parent: Id<P>) -> Vec<Id<C>> {
+        children.iter()
+            .filter(|&(_, child)| (self.get)(child) == Some(parent))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Apply the policy of this relation to all children referencing the parent,
+    /// without removing the parent itself.
+    /// Use `Relations` to remove a parent which is referenced by multiple relations.
+    pub fn on_delete(&self, children: &mut IdVec<C>, parent: Id<P>) -> Result<Affected<C>, Restricted<C>> {
+        let referencing = self.children_of(children, parent);
+        let mut cascaded = Vec::new();
+        let mut cleared = Vec::new();
+
+        match self.policy {
+            OnDelete::Restrict => if !referencing.is_empty() {
+                return Err(Restricted { children: referencing });
+            },
+
+            OnDelete::Cascade => for child in referencing {
+                let element = children.remove(child).expect("referencing child not found");
+                cascaded.push((child, element));
+            },
+
+            OnDelete::SetNone => for child in referencing {
+                (self.set)(&mut children[child], None);
+                cleared.push(child);
+            },
+        }
+
+        Ok(Affected { cascaded, cleared })
+    }
+
+    /// Fails if the relation is `OnDelete::Restrict` and any child references the parent
+    pub fn restrict(&self, children: &IdVec<C>, parent: Id<P>) -> Result<(), Restricted<C>> {
+        if self.policy == OnDelete::Restrict {
+            let referencing = self.children_of(children, parent);
+            if !referencing.is_empty() {
+                return Err(Restricted { children: referencing });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the parent and apply the policy of this relation to all children referencing it.
+    /// Nothing is modified if the relation restricts the removal.
+    /// Returns `Ok(None)` for invalid and deleted ids.
+    pub fn remove(&self, parents: &mut IdVec<P>, children: &mut IdVec<C>, parent: Id<P>)
+        -> Result<Option<Removal<P, C>>, Restricted<C>>
+    {
+        if !parents.contains_id(parent) {
+            return Ok(None);
+        }
+
+        let affected = self.on_delete(children, parent)?;
+        let parent = parents.remove(parent).expect("parent not found");
+        Ok(Some(Removal { parent, children: affected }))
+    }
+}
+
+
+impl<P, C> Clone for Relation<P, C> {
+    fn clone(&self) -> Self {
+        Relation { get: self.get, set: self.set, policy: self.policy }
+    }
+}
+
+impl<'s, P> Relations<'s, P> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        Relations { links: Vec::new() }
+    }
+
+    /// Add a relation together with the children it describes
+    pub fn with<C: 's>(mut self, relation: Relation<P, C>, children: &'s mut IdVec<C>) -> Self where P: 's {
+        self.links.push(Box::new(BoundRelation { relation, children }));
+        self
+    }
+
+    /// Fails with the first relation that restricts the removal of the parent
+    pub fn restrict(&self, parent: Id<P>) -> Result<(), RestrictedBy> {
+        for (relation, link) in self.links.iter().enumerate() {
+            let children = link.restricting_children(parent);
+            if !children.is_empty() {
+                return Err(RestrictedBy { relation, children });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the parent and apply the policies of all relations to the children referencing it.
+    /// Nothing is modified if any relation restricts the removal.
+    /// Children removed by `OnDelete::Cascade` are dropped, without applying the relations that reference them.
+    /// Returns `Ok(None)` for invalid and deleted ids.
+    pub fn remove(&mut self, parents: &mut IdVec<P>, parent: Id<P>) -> Result<Option<P>, RestrictedBy> {
+        if !parents.contains_id(parent) {
+            return Ok(None);
+        }
+
+        self.restrict(parent)?;
+
+        for link in &mut self.links {
+            link.on_delete(parent);
+        }
+
+        Ok(parents.remove(parent))
+    }
+}
+
+impl<'s, P> Default for Relations<'s, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, C> ::std::fmt::Debug for Relation<P, C> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "Relation {{ policy: {:?} }}", self.policy)
+    }
+}
+
+
+impl<C> Eq for Restricted<C> {}
+impl<C> PartialEq for Restricted<C> {
+    fn eq(&self, other: &Restricted<C>) -> bool {
+        self.children == other.children
+    }
+}
+impl<C> Clone for Restricted<C> {
+    fn clone(&self) -> Self {
+        Restricted { children: self.children.clone() }
+    }
+}
+impl<C> ::std::fmt::Debug for Restricted<C> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "Restricted {{ children: {:?} }}", self.children)
+    }
+}
+impl<C> ::std::fmt::Display for Restricted<C> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "cannot remove element which is still referenced by {:?}", self.children)
+    }
+}
+impl<C> ::std::error::Error for Restricted<C> {}
+
+impl ::std::fmt::Display for RestrictedBy {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "cannot remove element which is still referenced by relation {}", self.relation)
+    }
+}
+impl ::std::error::Error for RestrictedBy {}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Material { name: &'static str }
+
+    #[derive(Debug, PartialEq)]
+    struct Mesh { material: Option<Id<Material>> }
+
+    fn scene() -> (IdVec<Material>, IdVec<Mesh>, Id<Material>) {
+        let mut materials = IdVec::new();
+        let metal = materials.insert(Material { name: "metal" });
+        let wood = materials.insert(Material { name: "wood" });
+
+        let meshes = id_vec!(
+            Mesh { material: Some(metal) },
+            Mesh { material: Some(wood) },
+            Mesh { material: Some(metal) },
+            Mesh { material: None }
+        );
+
+        (materials, meshes, metal)
+    }
+
+    fn material_of_mesh(policy: OnDelete) -> Relation<Material, Mesh> {
+        Relation::new(|mesh: &Mesh| mesh.material, |mesh, material| mesh.material = material, policy)
+    }
+
+    #[test]
+    pub fn test_restrict(){
+        let (mut materials, mut meshes, metal) = scene();
+        let relation = material_of_mesh(OnDelete::Restrict);
+
+        let error = relation.remove(&mut materials, &mut meshes, metal).err().unwrap();
+        assert_eq!(error.children, vec![ Id::from_index(0), Id::from_index(2) ]);
+        assert!(materials.contains_id(metal), "restricted removal does not modify");
+
+        meshes.remove(Id::from_index(0));
+        meshes.remove(Id::from_index(2));
+        assert!(relation.restrict(&meshes, metal).is_ok());
+
+        let removal = relation.remove(&mut materials, &mut meshes, metal).ok().unwrap().unwrap();
+        assert_eq!(removal.parent, Material { name: "metal" });
+        assert!(!materials.contains_id(metal));
+    }
+
+    #[test]
+    pub fn test_cascade(){
+        let (mut materials, mut meshes, metal) = scene();
+        let relation = material_of_mesh(OnDelete::Cascade);
+
+        let removal = relation.remove(&mut materials, &mut meshes, metal).ok().unwrap().unwrap();
+        assert_eq!(removal.children.cascaded.len(), 2);
+        assert_eq!(removal.children.cascaded[0], (Id::from_index(0), Mesh { material: Some(metal) }));
+        assert_eq!(meshes.ids().map(|id| id.index_value()).collect::<Vec<_>>(), vec![1, 3]);
+
+        assert!(relation.remove(&mut materials, &mut meshes, metal).ok().unwrap().is_none(), "removing twice");
+    }
+
+    #[test]
+    pub fn test_set_none(){
+        let (mut materials, mut meshes, metal) = scene();
+        let relation = material_of_mesh(OnDelete::SetNone);
+
+        let removal = relation.remove(&mut materials, &mut meshes, metal).ok().unwrap().unwrap();
+        assert_eq!(removal.children.cleared, vec![ Id::from_index(0), Id::from_index(2) ]);
+        assert_eq!(meshes.len(), 4);
+        assert_eq!(relation.parent_of(&meshes, Id::from_index(0)), None);
+        assert_eq!(relation.parent_of(&meshes, Id::from_index(1)), Some(Id::from_index(1)));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Light { material: Option<Id<Material>> }
+
+    #[test]
+    pub fn test_remove_through_all_relations(){
+        let (mut materials, mut meshes, metal) = scene();
+        let mut lights = id_vec!(Light { material: Some(metal) }, Light { material: None });
+        let material_of_light = || Relation::new(
+            |light: &Light| light.material, |light, material| light.material = material, OnDelete::SetNone
+        );
+
+        let restricting = Relations::new()
+            .with(material_of_light(), &mut lights)
+            .with(material_of_mesh(OnDelete::Restrict), &mut meshes)
+            .remove(&mut materials, metal);
+
+        assert_eq!(restricting, Err(RestrictedBy { relation: 1, children: vec![0, 2] }));
+        assert!(materials.contains_id(metal));
+        assert_eq!(lights[Id::from_index(0)].material, Some(metal), "restricted removal does not modify any relation");
+
+        let removed = Relations::new()
+            .with(material_of_light(), &mut lights)
+            .with(material_of_mesh(OnDelete::Cascade), &mut meshes)
+            .remove(&mut materials, metal);
+
+        assert_eq!(removed, Ok(Some(Material { name: "metal" })));
+        assert_eq!(lights[Id::from_index(0)].material, None);
+        assert_eq!(meshes.ids().map(|id| id.index_value()).collect::<Vec<_>>(), vec![1, 3]);
+    }
+}
@@ -0,0 +1,391 @@
+
+use ::std::collections::BTreeMap;
+
+
+/// Identifies a replica. Every replica of the same vector must use a different site id.
+pub type SiteId = u64;
+
+
+/// Identifies an element of a `ReplicatedIdVec`. Created by exactly one replica,
+/// which makes it unique across all replicas without any coordination.
+/// Ordered by creation time first, so iteration roughly follows insertion order.
+// manually implementing hash, clone, copy, eq, ord, because T does not need to implement them
+pub struct ReplicatedId<T> {
+    counter: u64,
+    site: SiteId,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+/// A Lamport timestamp, deciding which of two concurrent updates wins.
+/// Ties are broken by the site id, so all replicas decide the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Timestamp {
+    pub counter: u64,
+    pub site: SiteId,
+}
+
+
+/// A modification of a `ReplicatedIdVec`, which can be sent to other replicas.
+/// Serializable with the `serde` feature.
+/// Operations can be applied in any order and any number of times,
+/// all replicas which have applied the same set of operations contain the same elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Operation<T> {
+    /// Insert a new element, or replace the element if the timestamp is newer
+    Set { id: ReplicatedId<T>, element: T, timestamp: Timestamp },
+
+    /// Remove the element permanently, ignoring all updates of it
+    Remove { id: ReplicatedId<T> },
+}
+
+
+/// A slot of a replica, which may be removed
+struct Entry<T> {
+    /// `None` if removed, to ignore updates which arrive after the removal
+    element: Option<T>,
+    timestamp: Timestamp,
+}
+
+
+/// An IdVec which is replicated across multiple sites, for example for collaborative editing.
+/// Each replica can be modified independently, and the modifications are exchanged as `Operation`s.
+/// Concurrent updates of the same element are resolved by keeping the newest one (last writer wins),
+/// and removal always wins over concurrent updates.
+///
+/// Removed elements are kept as tombstones, so that ids are never reused.
+pub struct ReplicatedIdVec<T> {
+    site: SiteId,
+
+    /// The Lamport clock, greater than all timestamps seen so far
+    clock: u64,
+
+    entries: BTreeMap<ReplicatedId<T>, Entry<T>>,
+    len: usize,
+
+    /// All operations created by this replica, oldest first
+    local_operations: Vec<Operation<T>>,
+}
+
+
+impl<T> ReplicatedId<T> {
+
+    /// The replica which created this id
+    pub fn site(self) -> SiteId {
+        self.site
+    }
+
+    /// The clock of the creating replica at the time of creation
+    pub fn counter(self) -> u64 {
+        self.counter
+    }
+
+    /// Reconstruct an id, for example after deserializing it
+    pub fn from_parts(site: SiteId, counter: u64) -> Self {
+        ReplicatedId { counter, site, _marker: ::std::marker::PhantomData }
+    }
+}
+
+
+impl<T> ReplicatedIdVec<T> where T: Clone {
+
+    /// Does not allocate heap memory. The site id must be unique among all replicas.
+    pub fn new(site: SiteId) -> Self {
+        ReplicatedIdVec { site, clock: 0, entries: BTreeMap::new(), len: 0, local_operations: Vec::new() }
+    }
+
+    pub fn site(&self) -> SiteId {
+        self.site
+    }
+
+    /// The number of elements, excluding removed elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Excludes removed elements and ids which have not been received yet
+    pub fn contains_id(&self, id: ReplicatedId<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: ReplicatedId<T>) -> Option<&T> {
+        self.entries.get(&id).and_then(|entry| entry.element.as_ref())
+    }
+
+    /// Iterate over all elements, ordered by id, which is the same order on all replicas
+    pub fn iter(&self) -> impl Iterator<Item = (ReplicatedId<T>, &T)> + '_ {
+        self.entries.iter()
+            .filter_map(|(&id, entry)| entry.element.as_ref().map(|element| (id, element)))
+    }
+
+    fn tick(&mut self) -> Timestamp {
+        self.clock += 1;
+        Timestamp { counter: self.clock, site: self.site }
+    }
+
+    fn apply_local(&mut self, operation: Operation<T>) {
+        self.apply(operation.clone());
+        self.local_operations.push(operation);
+    }
+
+
+
+    /// Insert the element with a new id, which is unique across all replicas
+    pub fn insert(&mut self, element: T) -> ReplicatedId<T> {
+        let timestamp = self.tick();
+        let id = ReplicatedId::from_parts(self.site, timestamp.counter);
+        self.apply_local(Operation::Set { id, element, timestamp });
+        id
+    }
+
+    /// Replace the element. Returns false for removed and unknown ids.
+    pub fn update(&mut self, id: ReplicatedId<T>, element: T) -> bool {
+        if !self.contains_id(id) {
+            return false;
+        }
+
+        let timestamp = self.tick();
+        self.apply_local(Operation::Set { id, element, timestamp });
+        true
+    }
+
+    /// Remove the element on all replicas. Returns false for removed and unknown ids.
+    pub fn remove(&mut self, id: ReplicatedId<T>) -> bool {
+        if !self.contains_id(id) {
+            return false;
+        }
+
+        self.apply_local(Operation::Remove { id });
+        true
+    }
+
+
+
+    /// Apply an operation created by any replica, including this one.
+    /// Applying an operation multiple times has the same effect as applying it once.
+    pub fn apply(&mut self, operation: Operation<T>) {
+        match operation {
+            Operation::Set { id, element, timestamp } => {
+                self.clock = self.clock.max(timestamp.counter);
+
+                match self.entries.get_mut(&id) {
+                    // last writer wins, removed elements stay removed
+                    Some(entry) => if entry.element.is_some() && timestamp > entry.timestamp {
+                        entry.element = Some(element);
+                        entry.timestamp = timestamp;
+                    },
+
+                    None => {
+                        self.entries.insert(id, Entry { element: Some(element), timestamp });
+                        self.len += 1;
+                    },
+                }
+            },
+
+            Operation::Remove { id } => {
+                let entry = self.entries.entry(id)
+                    .or_insert(Entry { element: None, timestamp: Timestamp { counter: 0, site: id.site } });
+
+                if entry.element.take().is_some() {
+                    self.len -= 1;
+                }
+            },
+        }
+    }
+
+    /// Apply all operations received from another replica
+    pub fn import<I>(&mut self, operations: I) where I: IntoIterator<Item = Operation<T>> {
+        for operation in operations {
+            self.apply(operation);
+        }
+    }
+
+    /// All operations created by this replica, starting at the specified position in the local log.
+    /// Each peer can remember how many operations it has already received.
+    pub fn export(&self, since: usize) -> &[Operation<T>] {
+        &self.local_operations[since.min(self.local_operations.len())..]
+    }
+
+    /// The number of operations created by this replica
+    pub fn local_operation_count(&self) -> usize {
+        self.local_operations.len()
+    }
+}
+
+impl<T> ::std::ops::Index<ReplicatedId<T>> for ReplicatedIdVec<T> where T: Clone {
+    type Output = T;
+    fn index(&self, id: ReplicatedId<T>) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("Indexing with invalid Id: `{:?}` ", id))
+    }
+}
+
+
+
+impl<T> Eq for ReplicatedId<T> {}
+impl<T> PartialEq for ReplicatedId<T> {
+    fn eq(&self, other: &ReplicatedId<T>) -> bool {
+        self.counter == other.counter && self.site == other.site
+    }
+}
+impl<T> Ord for ReplicatedId<T> {
+    fn cmp(&self, other: &ReplicatedId<T>) -> ::std::cmp::Ordering {
+        (self.counter, self.site).cmp(&(other.counter, other.site))
+    }
+}
+impl<T> PartialOrd for ReplicatedId<T> {
+    fn partial_cmp(&self, other: &ReplicatedId<T>) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> Copy for ReplicatedId<T> {}
+impl<T> Clone for ReplicatedId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> ::std::hash::Hash for ReplicatedId<T> {
+    fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.site);
+        state.write_u64(self.counter);
+    }
+}
+impl<T> ::std::fmt::Debug for ReplicatedId<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "Id#{}@{}", self.counter, self.site)
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::collections::VecDeque;
+
+    /// Delivers operations between replicas in a fixed, but scrambled order
+    struct Channel {
+        queue: VecDeque<(usize, Operation<&'static str>)>,
+        sent: Vec<usize>,
+    }
+
+    impl Channel {
+        fn new(replicas: usize) -> Self {
+            Channel { queue: VecDeque::new(), sent: vec![0; replicas] }
+        }
+
+        /// Queue the new operations of each replica for all other replicas
+        fn send(&mut self, replicas: &[ReplicatedIdVec<&'static str>]) {
+            for (sender, replica) in replicas.iter().enumerate() {
+                for operation in replica.export(self.sent[sender]) {
+                    for receiver in (0 .. replicas.len()).filter(|&receiver| receiver != sender) {
+                        self.queue.push_back((receiver, operation.clone()));
+                    }
+                }
+
+                self.sent[sender] = replica.local_operation_count();
+            }
+        }
+
+        /// Deliver all queued operations, alternating between both ends of the queue
+        fn deliver(&mut self, replicas: &mut [ReplicatedIdVec<&'static str>]) {
+            let mut from_back = false;
+            while let Some((receiver, operation)) = if from_back { self.queue.pop_back() } else { self.queue.pop_front() } {
+                replicas[receiver].apply(operation.clone());
+                replicas[receiver].apply(operation); // duplicates are ignored
+                from_back = !from_back;
+            }
+        }
+    }
+
+    fn contents(replica: &ReplicatedIdVec<&'static str>) -> Vec<(ReplicatedId<&'static str>, &'static str)> {
+        replica.iter().map(|(id, &element)| (id, element)).collect()
+    }
+
+    #[test]
+    pub fn test_concurrent_inserts_do_not_collide(){
+        let mut replicas = vec![ ReplicatedIdVec::new(0), ReplicatedIdVec::new(1), ReplicatedIdVec::new(2) ];
+        let a = replicas[0].insert("a");
+        let b = replicas[1].insert("b");
+        let c = replicas[2].insert("c");
+        assert!(a != b && b != c && a != c);
+
+        let mut channel = Channel::new(3);
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        assert_eq!(replicas[0].len(), 3);
+        assert_eq!(contents(&replicas[0]), contents(&replicas[1]));
+        assert_eq!(contents(&replicas[1]), contents(&replicas[2]));
+    }
+
+    #[test]
+    pub fn test_last_writer_wins(){
+        let mut replicas = vec![ ReplicatedIdVec::new(0), ReplicatedIdVec::new(1) ];
+        let id = replicas[0].insert("original");
+
+        let mut channel = Channel::new(2);
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        replicas[0].update(id, "first");
+        replicas[1].update(id, "second");
+        replicas[1].update(id, "third");
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        assert_eq!(replicas[0][id], "third");
+        assert_eq!(contents(&replicas[0]), contents(&replicas[1]));
+    }
+
+    #[test]
+    pub fn test_remove_wins_over_concurrent_update(){
+        let mut replicas = vec![ ReplicatedIdVec::new(0), ReplicatedIdVec::new(1) ];
+        let id = replicas[0].insert("a");
+        let kept = replicas[0].insert("b");
+
+        // the other replica receives the insertion, then the removal, then the other insertion
+        replicas[0].remove(id);
+        let mut channel = Channel::new(2);
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        assert!(!replicas[1].update(id, "updated"));
+        replicas[1].apply(Operation::Set { id, element: "late", timestamp: Timestamp { counter: 100, site: 1 } });
+
+        assert!(!replicas[1].contains_id(id));
+        assert_eq!(contents(&replicas[1]), vec![ (kept, "b") ]);
+        assert_eq!(contents(&replicas[0]), contents(&replicas[1]));
+    }
+
+    #[test]
+    pub fn test_concurrent_remove_and_update(){
+        let mut replicas = vec![ ReplicatedIdVec::new(0), ReplicatedIdVec::new(1) ];
+        let removed = replicas[0].insert("a");
+        let updated = replicas[0].insert("b");
+
+        let mut channel = Channel::new(2);
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        // both replicas modify before receiving the modifications of the other replica
+        assert!(replicas[0].remove(removed));
+        assert!(replicas[0].update(updated, "b0"));
+        assert!(replicas[1].update(removed, "a1"));
+        assert!(replicas[1].update(updated, "b1"));
+        let inserted = replicas[1].insert("c");
+
+        channel.send(&replicas);
+        channel.deliver(&mut replicas);
+
+        assert!(!replicas[0].contains_id(removed) && !replicas[1].contains_id(removed), "removal wins");
+        assert_eq!(replicas[0][inserted], "c");
+        assert_eq!(contents(&replicas[0]), contents(&replicas[1]));
+        assert_eq!(replicas[0].len(), 2);
+    }
+}
@@ -0,0 +1,100 @@
+
+use ::serde::{ Serialize, Serializer, Deserialize, Deserializer };
+use ::id::*;
+use ::vec::IdVec;
+use ::replicated::{ ReplicatedId, SiteId };
+
+
+/// Serialized as its index
+impl<T> Serialize for Id<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.index_value().serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Id<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Index::deserialize(deserializer).map(Id::from_index)
+    }
+}
+
+
+/// Serialized as a sequence of optional elements, where the position is the index of the id.
+/// Unused slots are serialized as `None`, so that all ids remain valid after deserializing.
+impl<T> Serialize for IdVec<T> where T: Serialize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let slots = (0 .. self.id_index_limit()).map(|index| self.get(Id::from_index(index)));
+        serializer.collect_seq(slots)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for IdVec<T> where T: Deserialize<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let slots: Vec<Option<T>> = Vec::deserialize(deserializer)?;
+        let mut vec = IdVec::with_capacity(slots.len());
+
+        for (index, slot) in slots.into_iter().enumerate() {
+            if let Some(element) = slot {
+                vec.insert_at(Id::from_index(index), element);
+            }
+        }
+
+        Ok(vec)
+    }
+}
+
+
+/// Serialized as a tuple of site and counter
+impl<T> Serialize for ReplicatedId<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.site(), self.counter()).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for ReplicatedId<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (site, counter) = <(SiteId, u64)>::deserialize(deserializer)?;
+        Ok(ReplicatedId::from_parts(site, counter))
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde_json;
+    use ::replicated::{ ReplicatedIdVec, Operation };
+
+    #[test]
+    pub fn test_id_vec_keeps_holes(){
+        let mut vec = id_vec!("a", "b", "c", "d");
+        vec.remove(Id::from_index(1));
+        vec.remove(Id::from_index(2));
+
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(json, r#"["a",null,null,"d"]"#);
+
+        let deserialized: IdVec<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized[Id::from_index(3)], "d");
+        assert_eq!(deserialized.len(), 2);
+        assert_eq!(deserialized.id_index_limit(), 4);
+        assert!(!deserialized.contains_id(Id::from_index(1)));
+    }
+
+    #[test]
+    pub fn test_ids_and_operations(){
+        let id: Id<f32> = Id::from_index(7);
+        assert_eq!(serde_json::to_string(&id).unwrap(), "7");
+        assert_eq!(serde_json::from_str::<Id<f32>>("7").unwrap(), id);
+
+        let mut replica = ReplicatedIdVec::new(3);
+        replica.insert(String::from("a"));
+        let json = serde_json::to_string(replica.export(0)).unwrap();
+
+        let operations: Vec<Operation<String>> = serde_json::from_str(&json).unwrap();
+        let mut other = ReplicatedIdVec::new(4);
+        other.import(operations);
+        assert_eq!(other.iter().collect::<Vec<_>>(), replica.iter().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,365 @@
+
+use ::std::sync::Arc;
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// The number of slots which are copied together when modifying a shared chunk
+const CHUNK_SIZE: usize = 64;
+
+/// A fixed-size part of the slots, shared between the vector and its snapshots
+type Chunk<T> = Arc<Vec<Option<T>>>;
+
+
+/// An IdVec whose storage can be shared with immutable snapshots.
+/// Taking a snapshot is O(1), and modifying the vector afterwards
+/// only copies the chunks of storage that are modified, in addition to the list of chunks.
+///
+/// The `IdVec` itself does not share its storage, because that would require
+/// all elements to be cloneable even if no snapshot is ever taken.
+#[derive(Clone)]
+pub struct SnapshotIdVec<T> {
+    chunks: Arc<Vec<Chunk<T>>>,
+
+    /// The number of slots, used and unused
+    slot_count: usize,
+
+    /// The number of used slots
+    len: usize,
+
+    /// Unused slots, reused by the next insertion, newest at the back
+    unused_indices: Vec<Index>,
+}
+
+/// An immutable view of a `SnapshotIdVec` at the time of the snapshot.
+/// Can be sent to other threads, while the vector continues to be modified.
+/// Cloning a snapshot is O(1).
+pub struct Snapshot<T> {
+    chunks: Arc<Vec<Chunk<T>>>,
+    slot_count: usize,
+    len: usize,
+}
+
+
+fn chunk_position(index: Index) -> (usize, usize) {
+    (index / CHUNK_SIZE, index % CHUNK_SIZE)
+}
+
+fn get_slot<T>(chunks: &[Chunk<T>], index: Index) -> Option<&T> {
+    let (chunk, slot) = chunk_position(index);
+    chunks.get(chunk).and_then(|chunk| chunk[slot].as_ref())
+}
+
+fn iter_slots<'s, T: 's>(chunks: &'s [Chunk<T>], slot_count: usize) -> impl Iterator<Item = (Id<T>, &'s T)> + 's {
+    chunks.iter().flat_map(|chunk| chunk.iter()).take(slot_count).enumerate()
+        .filter_map(|(index, slot)| slot.as_ref().map(|element| (Id::from_index(index), element)))
+}
+
+
+impl<T> SnapshotIdVec<T> where T: Clone {
+
+    /// Does not allocate heap memory for elements
+    pub fn new() -> Self {
+        SnapshotIdVec { chunks: Arc::new(Vec::new()), slot_count: 0, len: 0, unused_indices: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// This vector will not contain an id with an index value greater than or equal to this value.
+    pub fn id_index_limit(&self) -> usize {
+        self.slot_count
+    }
+
+    /// Excludes deleted elements, and indices out of range
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Create an immutable view of the current state, in O(1)
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot { chunks: self.chunks.clone(), slot_count: self.slot_count, len: self.len }
+    }
+
+    /// Mutable access to a slot, copying the chunk if it is shared with a snapshot
+    fn slot_mut(&mut self, index: Index) -> &mut Option<T> {
+        let (chunk, slot) = chunk_position(index);
+        let chunks = Arc::make_mut(&mut self.chunks);
+
+        while chunks.len() <= chunk {
+            let mut new_chunk = Vec::with_capacity(CHUNK_SIZE);
+            new_chunk.resize_with(CHUNK_SIZE, || None);
+            chunks.push(Arc::new(new_chunk));
+        }
+
+        &mut Arc::make_mut(&mut chunks[chunk])[slot]
+    }
+
+
+
+    /// Associate the specified element with a currently unused id
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        let index = self.unused_indices.pop().unwrap_or_else(|| {
+            self.slot_count += 1;
+            self.slot_count - 1
+        });
+
+        *self.slot_mut(index) = Some(element);
+        self.len += 1;
+        Id::from_index(index)
+    }
+
+    /// Enable the specified id to be overwritten when a new element is inserted.
+    /// Returns the removed element. Ignores invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) -> Option<T> {
+        if !self.contains_id(id) {
+            return None;
+        }
+
+        self.len -= 1;
+        self.unused_indices.push(id.index_value());
+        self.slot_mut(id.index_value()).take()
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        get_slot(&self.chunks, id.index_value())
+    }
+
+    /// Return a mutable reference to the element that this id points to.
+    /// Copies the containing chunk if it is shared with a snapshot.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        if self.contains_id(id) {
+            self.slot_mut(id.index_value()).as_mut()
+        } else { None }
+    }
+
+    /// Used for immutable access to ids and elements
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item = (Id<T>, &'s T)> + 's {
+        iter_slots(&self.chunks, self.slot_count)
+    }
+
+    /// Copy all elements into an IdVec, keeping their ids.
+    /// Chunks which are not shared with a snapshot are moved instead of copied.
+    pub fn into_id_vec(self) -> IdVec<T> {
+        let mut vec = IdVec::with_capacity(self.slot_count);
+        let chunks = Arc::try_unwrap(self.chunks).unwrap_or_else(|shared| (*shared).clone());
+
+        let slots = chunks.into_iter()
+            .flat_map(|chunk| Arc::try_unwrap(chunk).unwrap_or_else(|shared| (*shared).clone()));
+
+        for (index, slot) in slots.enumerate() {
+            if let Some(element) = slot {
+                vec.insert_at(Id::from_index(index), element);
+            }
+        }
+
+        vec
+    }
+}
+
+
+impl<T> Snapshot<T> {
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// This snapshot does not contain an id with an index value greater than or equal to this value.
+    pub fn id_index_limit(&self) -> usize {
+        self.slot_count
+    }
+
+    /// Excludes deleted elements, and indices out of range
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Return a reference to the element that this id pointed to at the time of the snapshot
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        get_slot(&self.chunks, id.index_value())
+    }
+
+    /// Used for immutable access to ids and elements
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item = (Id<T>, &'s T)> + 's {
+        iter_slots(&self.chunks, self.slot_count)
+    }
+}
+
+
+impl<T> IdVec<T> where T: Clone {
+
+    /// Copy all elements into an immutable view, which can be sent to other threads.
+    /// This is a full copy in O(n), not a copy-on-write snapshot: a plain `IdVec` owns its storage exclusively,
+    /// as sharing it would require every element type to be cloneable.
+    /// Convert the vector into a `SnapshotIdVec` to take repeated snapshots in O(1).
+    pub fn to_snapshot(&self) -> Snapshot<T> {
+        let chunks = (0 .. self.id_index_limit()).step_by(CHUNK_SIZE)
+            .map(|start| Arc::new(
+                (start .. start + CHUNK_SIZE).map(|index| self.get(Id::from_index(index)).cloned()).collect()
+            ))
+            .collect();
+
+        Snapshot { chunks: Arc::new(chunks), slot_count: self.id_index_limit(), len: self.len() }
+    }
+}
+
+impl<T> Default for SnapshotIdVec<T> where T: Clone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<IdVec<T>> for SnapshotIdVec<T> where T: Clone {
+    fn from(vec: IdVec<T>) -> Self {
+        let mut snapshot_vec = SnapshotIdVec::new();
+        snapshot_vec.slot_count = vec.id_index_limit();
+
+        // insert in reverse order, so that the lowest unused ids are reused first
+        for index in (0 .. vec.id_index_limit()).rev() {
+            if !vec.contains_id(Id::from_index(index)) {
+                snapshot_vec.unused_indices.push(index);
+            }
+        }
+
+        for (id, element) in vec.get_ids().zip(vec.into_elements()) {
+            *snapshot_vec.slot_mut(id.index_value()) = Some(element);
+            snapshot_vec.len += 1;
+        }
+
+        snapshot_vec
+    }
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Self {
+        Snapshot { chunks: self.chunks.clone(), slot_count: self.slot_count, len: self.len }
+    }
+}
+
+impl<T> ::std::ops::Index<Id<T>> for SnapshotIdVec<T> where T: Clone {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("Indexing with invalid Id: `{:?}` ", id))
+    }
+}
+
+impl<T> ::std::ops::IndexMut<Id<T>> for SnapshotIdVec<T> where T: Clone {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        self.get_mut(id).unwrap_or_else(|| panic!("Indexing-Mut with invalid Id: `{:?}` ", id))
+    }
+}
+
+impl<T> ::std::ops::Index<Id<T>> for Snapshot<T> {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("Indexing with invalid Id: `{:?}` ", id))
+    }
+}
+
+use ::std::fmt::Debug;
+impl<T> Debug for Snapshot<T> where T: Debug {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(formatter, "{{ ")?;
+
+        for (id, element) in self.iter() {
+            write!(formatter, "{:?}: {:?}, ", id, element)?;
+        }
+
+        write!(formatter, "}}")
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_snapshot_is_unaffected_by_modification(){
+        let mut vec = SnapshotIdVec::new();
+        let a = vec.insert(String::from("a"));
+        let b = vec.insert(String::from("b"));
+
+        let snapshot = vec.snapshot();
+        vec[a].push('!');
+        vec.remove(b);
+        let c = vec.insert(String::from("c"));
+        assert_eq!(c, b, "reusing removed id");
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[a], "a");
+        assert_eq!(snapshot[b], "b");
+        assert_eq!(vec[a], "a!");
+        assert_eq!(vec[c], "c");
+    }
+
+    #[test]
+    pub fn test_only_modified_chunks_are_copied(){
+        let mut vec: SnapshotIdVec<usize> = (0 .. 3 * CHUNK_SIZE).collect::<IdVec<_>>().into();
+        let snapshot = vec.snapshot();
+        assert!(Arc::ptr_eq(&vec.chunks, &snapshot.chunks));
+
+        vec[Id::from_index(CHUNK_SIZE + 1)] = 0;
+        assert!(Arc::ptr_eq(&vec.chunks[0], &snapshot.chunks[0]));
+        assert!(!Arc::ptr_eq(&vec.chunks[1], &snapshot.chunks[1]));
+        assert!(Arc::ptr_eq(&vec.chunks[2], &snapshot.chunks[2]));
+    }
+
+    #[test]
+    pub fn test_snapshot_on_other_thread(){
+        let mut vec = SnapshotIdVec::new();
+        for index in 0 .. 100 { vec.insert(index); }
+
+        let snapshot = vec.snapshot();
+        let reader = ::std::thread::spawn(move || {
+            snapshot.iter().map(|(_, &element)| element).sum::<i32>()
+        });
+
+        for index in 0 .. 100 { vec[Id::from_index(index)] = 0; }
+        assert_eq!(reader.join().unwrap(), (0 .. 100).sum::<i32>());
+    }
+
+    #[test]
+    pub fn test_id_vec_to_snapshot(){
+        let mut vec: IdVec<usize> = (0 .. CHUNK_SIZE + 2).collect();
+        vec.remove(Id::from_index(1));
+
+        let snapshot = vec.to_snapshot();
+        vec[Id::from_index(0)] = 7;
+        vec.remove(Id::from_index(CHUNK_SIZE + 1));
+
+        assert_eq!(snapshot.len(), CHUNK_SIZE + 1);
+        assert_eq!(snapshot.id_index_limit(), CHUNK_SIZE + 2);
+        assert_eq!(snapshot[Id::from_index(0)], 0);
+        assert!(!snapshot.contains_id(Id::from_index(1)));
+        assert_eq!(snapshot[Id::from_index(CHUNK_SIZE + 1)], CHUNK_SIZE + 1);
+        assert_eq!(snapshot.iter().count(), CHUNK_SIZE + 1);
+    }
+
+    #[test]
+    pub fn test_id_vec_conversion(){
+        let mut vec = id_vec!(0, 1, 2, 3);
+        vec.remove(Id::from_index(1));
+
+        let mut snapshot_vec = SnapshotIdVec::from(vec.clone());
+        assert_eq!(snapshot_vec.len(), 3);
+        assert!(!snapshot_vec.contains_id(Id::from_index(1)));
+        assert_eq!(snapshot_vec.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec.ids().collect::<Vec<_>>());
+
+        let _snapshot = snapshot_vec.snapshot();
+        assert_eq!(snapshot_vec.insert(4), Id::from_index(1));
+        vec.insert(4);
+        assert_eq!(snapshot_vec.into_id_vec(), vec);
+    }
+}
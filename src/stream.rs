@@ -0,0 +1,194 @@
+
+use ::std::io::{ self, Read, Write };
+use ::id::*;
+use ::vec::IdVec;
+use ::binary::{ Encode, Decode, DecodeError, write_header, read_header, write_length, read_index, check_unused_slots };
+
+
+/// The first bytes of every stream written by `IdVecWriter`
+const MAGIC: [u8; 4] = *b"IDVS";
+
+
+/// Writes ids and elements to a sink one at a time, without collecting them first.
+/// Each record consists of the id index plus one, followed by the element.
+/// `finish` must be called to write the end marker, otherwise reading reports a truncated stream.
+/// Wrap the sink in a `BufWriter` to avoid many small writes.
+pub struct IdVecWriter<W, T> {
+    write: W,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+/// Reads ids and elements written by `IdVecWriter` one at a time.
+/// Iterating yields each record lazily, while `into_id_vec` rebuilds the vector with identical ids.
+/// Wrap the source in a `BufReader` to avoid many small reads.
+pub struct IdVecReader<R, T> {
+    read: R,
+
+    /// Set after the end marker or an error has been read
+    finished: bool,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+
+impl<W, T> IdVecWriter<W, T> where W: Write, T: Encode {
+
+    /// Write the header of the stream
+    pub fn new(mut write: W) -> io::Result<Self> {
+        write_header(&mut write, MAGIC)?;
+        Ok(IdVecWriter { write, _marker: ::std::marker::PhantomData })
+    }
+
+    /// Append a single record. Each id must be written at most once, in any order.
+    pub fn write(&mut self, id: Id<T>, element: &T) -> io::Result<()> {
+        write_length(&mut self.write, id.index_value() as u64 + 1)?;
+        element.encode(&mut self.write)
+    }
+
+    /// Append all ids and elements of the vector
+    pub fn write_all(&mut self, vec: &IdVec<T>) -> io::Result<()> {
+        for (id, element) in vec.iter() {
+            self.write(id, element)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the end marker and flush the sink, returning it
+    pub fn finish(mut self) -> io::Result<W> {
+        write_length(&mut self.write, 0)?;
+        self.write.flush()?;
+        Ok(self.write)
+    }
+}
+
+
+impl<R, T> IdVecReader<R, T> where R: Read, T: Decode {
+
+    /// Read and validate the header of the stream
+    pub fn new(mut read: R) -> Result<Self, DecodeError> {
+        read_header(&mut read, MAGIC)?;
+        Ok(IdVecReader { read, finished: false, _marker: ::std::marker::PhantomData })
+    }
+
+    fn read_record(&mut self) -> Result<Option<(Id<T>, T)>, DecodeError> {
+        let index = match read_index(&mut self.read)? {
+            0 => return Ok(None), // end marker
+            index => index - 1,
+        };
+
+        let element = T::decode(&mut self.read)?;
+        Ok(Some((Id::from_index(index), element)))
+    }
+
+    /// Read all remaining records, placing each element at exactly its id as soon as it is decoded.
+    /// Fails if the stream contains an id more than once, or if after any record
+    /// the ids so far leave more than a million slots unused, and more unused than used slots.
+    /// Streams written in the order of their ids, like by `write_all`, only fail if the whole vector would.
+    pub fn into_id_vec(self) -> Result<IdVec<T>, DecodeError> {
+        let mut vec = IdVec::new();
+
+        for record in self {
+            let (id, element) = record?;
+
+            // the id is only trusted after checking it, before allocating any slots
+            let id_index_limit = vec.id_index_limit().max(id.index_value() + 1);
+            check_unused_slots(id_index_limit, (vec.len() + 1).min(id_index_limit))?;
+
+            if vec.insert_at(id, element).is_some() {
+                return Err(DecodeError::InvalidData("duplicate id"));
+            }
+        }
+
+        Ok(vec)
+    }
+}
+
+impl<R, T> Iterator for IdVecReader<R, T> where R: Read, T: Decode {
+    type Item = Result<(Id<T>, T), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let record = self.read_record();
+        self.finished = !matches!(record, Ok(Some(_)));
+        record.transpose()
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(vec: &IdVec<u32>) -> Vec<u8> {
+        let mut writer = IdVecWriter::new(Vec::new()).unwrap();
+        writer.write_all(vec).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    pub fn test_rebuild_with_identical_ids(){
+        let mut vec = id_vec!(0, 1, 2, 3, 4);
+        vec.remove(Id::from_index(0));
+        vec.remove(Id::from_index(3));
+
+        let bytes = write(&vec);
+        let reader: IdVecReader<_, u32> = IdVecReader::new(bytes.as_slice()).unwrap();
+        let rebuilt = reader.into_id_vec().unwrap();
+        assert_eq!(rebuilt, vec);
+        assert!(!rebuilt.contains_id(Id::from_index(0)));
+        assert!(!rebuilt.contains_id(Id::from_index(3)));
+    }
+
+    #[test]
+    pub fn test_lazy_records_in_any_order(){
+        let mut writer = IdVecWriter::new(Vec::new()).unwrap();
+        writer.write(Id::from_index(5), &50_u32).unwrap();
+        writer.write(Id::from_index(2), &20).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let records = IdVecReader::<_, u32>::new(bytes.as_slice()).unwrap()
+            .map(|record| record.unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(records, vec![ (Id::from_index(5), 50), (Id::from_index(2), 20) ]);
+
+        let rebuilt = IdVecReader::<_, u32>::new(bytes.as_slice()).unwrap().into_id_vec().unwrap();
+        assert_eq!(rebuilt.len(), 2);
+        assert_eq!(rebuilt[Id::from_index(2)], 20);
+    }
+
+    #[test]
+    pub fn test_invalid_streams(){
+        let bytes = write(&id_vec!(7, 8));
+        let truncated = IdVecReader::<_, u32>::new(&bytes[.. bytes.len() - 1]).unwrap().into_id_vec();
+        assert!(matches!(truncated, Err(DecodeError::Truncated)), "missing end marker");
+
+        let mut writer = IdVecWriter::new(Vec::new()).unwrap();
+        writer.write(Id::from_index(1), &1_u32).unwrap();
+        writer.write(Id::from_index(1), &2).unwrap();
+        let duplicate = IdVecReader::<_, u32>::new(writer.finish().unwrap().as_slice()).unwrap().into_id_vec();
+        assert!(matches!(duplicate, Err(DecodeError::InvalidData(_))));
+
+        let mut writer = IdVecWriter::new(Vec::new()).unwrap();
+        writer.write(Id::from_index(1 << 62), &1_u32).unwrap();
+        let huge_id = IdVecReader::<_, u32>::new(writer.finish().unwrap().as_slice()).unwrap().into_id_vec();
+        assert!(matches!(huge_id, Err(DecodeError::InvalidData(_))), "too many unused slots");
+
+        let mut writer = IdVecWriter::new(Vec::new()).unwrap();
+        writer.write(Id::from_index(0), &0_u32).unwrap();
+        writer.write(Id::from_index(1 << 62), &1).unwrap();
+        writer.write(Id::from_index(1), &2).unwrap();
+        let bytes = writer.finish().unwrap();
+        let mut remaining = bytes.as_slice();
+        let huge_id = IdVecReader::<_, u32>::new(&mut remaining).unwrap().into_id_vec();
+        assert!(matches!(huge_id, Err(DecodeError::InvalidData(_))), "too many unused slots after the second record");
+        assert!(!remaining.is_empty(), "stops before reading the third record");
+
+        let mut plain = Vec::new();
+        id_vec!(1_u32).write_to(&mut plain).unwrap();
+        assert!(matches!(IdVecReader::<_, u32>::new(plain.as_slice()), Err(DecodeError::InvalidHeader)));
+    }
+}
@@ -0,0 +1,163 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Associates values with some of the ids of an `IdVec<T>`, without hashing.
+/// Internally, this is a vector indexed by the ids,
+/// so it should be created with `IdTable::for_vec` to avoid reallocation.
+// manually implementing clone, because T does not need to implement it
+pub struct IdTable<T, V> {
+    values: Vec<Option<V>>,
+    len: usize,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+
+impl<T, V> IdTable<T, V> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        Self::with_id_index_limit(0)
+    }
+
+    /// Allocates space for all ids with an index value below the limit
+    pub fn with_id_index_limit(limit: usize) -> Self {
+        let mut values = Vec::with_capacity(limit);
+        values.resize_with(limit, || None);
+        IdTable { values, len: 0, _marker: ::std::marker::PhantomData }
+    }
+
+    /// Allocates space for all ids inside the vector
+    pub fn for_vec(vec: &IdVec<T>) -> Self {
+        Self::with_id_index_limit(vec.id_index_limit())
+    }
+
+    /// The number of ids which have an associated value
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Return a reference to the value associated with this id
+    pub fn get(&self, id: Id<T>) -> Option<&V> {
+        self.values.get(id.index_value()).and_then(Option::as_ref)
+    }
+
+    /// Return a mutable reference to the value associated with this id
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut V> {
+        self.values.get_mut(id.index_value()).and_then(Option::as_mut)
+    }
+
+    /// Associate the value with the id, returning the previously associated value.
+    /// Grows the internal vector if the id is out of range.
+    pub fn insert(&mut self, id: Id<T>, value: V) -> Option<V> {
+        let index = id.index_value();
+        if index >= self.values.len() {
+            self.values.resize_with(index + 1, || None);
+        }
+
+        let previous = self.values[index].replace(value);
+        if previous.is_none() { self.len += 1; }
+        previous
+    }
+
+    /// Remove the value associated with the id, if any
+    pub fn remove(&mut self, id: Id<T>) -> Option<V> {
+        let removed = self.values.get_mut(id.index_value()).and_then(Option::take);
+        if removed.is_some() { self.len -= 1; }
+        removed
+    }
+
+    /// Removes all values, without deallocating
+    pub fn clear(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = None);
+        self.len = 0;
+    }
+
+    /// Used for immutable access to ids and values, ordered by id
+    pub fn iter<'s>(&'s self) -> impl DoubleEndedIterator<Item = (Id<T>, &'s V)> + 's {
+        self.values.iter().enumerate().filter_map(|(index, value)| {
+            value.as_ref().map(|value| (Id::from_index(index), value))
+        })
+    }
+
+    /// Used for immutable indirect access
+    pub fn ids<'s>(&'s self) -> impl DoubleEndedIterator<Item = Id<T>> + 's {
+        self.iter().map(|(id, _)| id)
+    }
+}
+
+
+impl<T, V> Default for IdTable<T, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, V> Clone for IdTable<T, V> where V: Clone {
+    fn clone(&self) -> Self {
+        IdTable { values: self.values.clone(), len: self.len, _marker: ::std::marker::PhantomData }
+    }
+}
+
+impl<T, V> ::std::ops::Index<Id<T>> for IdTable<T, V> {
+    type Output = V;
+    fn index(&self, id: Id<T>) -> &V {
+        self.get(id).unwrap_or_else(|| panic!("No value associated with `{:?}`", id))
+    }
+}
+
+impl<T, V> ::std::ops::IndexMut<Id<T>> for IdTable<T, V> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut V {
+        self.get_mut(id).unwrap_or_else(|| panic!("No value associated with `{:?}`", id))
+    }
+}
+
+use ::std::fmt::Debug;
+impl<T, V> Debug for IdTable<T, V> where V: Debug {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(formatter, "{{ ")?;
+
+        for (id, value) in self.iter() {
+            write!(formatter, "{:?}: {:?}, ", id, value)?;
+        }
+
+        write!(formatter, "}}")
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_insert_and_remove(){
+        let vec = id_vec!("a", "b", "c");
+        let mut table = IdTable::for_vec(&vec);
+        assert!(table.is_empty());
+
+        let b = Id::from_index(1);
+        assert_eq!(table.insert(b, 1.0), None);
+        assert_eq!(table.insert(b, 2.0), Some(1.0));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table[b], 2.0);
+
+        table.insert(Id::from_index(7), 4.0);
+        assert_eq!(table.ids().map(|id| id.index_value()).collect::<Vec<_>>(), vec![1, 7], "growing");
+
+        assert_eq!(table.remove(b), Some(2.0));
+        assert_eq!(table.remove(b), None);
+        assert_eq!(table.get(b), None);
+        assert_eq!(table.len(), 1);
+    }
+}
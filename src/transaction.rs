@@ -0,0 +1,218 @@
+
+use ::std::collections::HashSet;
+use ::id::*;
+use ::vec::IdVec;
+use ::journal::Change;
+
+
+/// Modifies an `IdVec` inside `IdVec::transaction`.
+/// All modifications are reverted if the transaction is dropped without being committed,
+/// which happens if the closure returns `Err` or panics.
+pub struct Transaction<'s, T: 's> {
+    elements: &'s mut IdVec<T>,
+
+    /// The inverse of each applied modification, newest at the back
+    undo: Vec<Change<T>>,
+
+    /// A copy of the unused ids before the first insertion or removal.
+    /// Undoing the changes alone would restore the same unused ids, but not the order in which they are reused,
+    /// so rolling back replaces them with this copy, which reuses the ids in exactly the previous order.
+    unused_indices: Option<HashSet<Index>>,
+}
+
+
+impl<T> IdVec<T> {
+
+    /// Run the closure, reverting all its modifications if it returns `Err` or panics.
+    /// Reverting restores the exact ids and unused slots of this vector.
+    /// Example: `vec.transaction(|tx| { let id = tx.insert(x); validate(&tx[id]) })`
+    pub fn transaction<R, E, F>(&mut self, modify: F) -> Result<R, E>
+        where F: FnOnce(&mut Transaction<'_, T>) -> Result<R, E>
+    {
+        let mut transaction = Transaction { elements: self, undo: Vec::new(), unused_indices: None };
+        let result = modify(&mut transaction);
+
+        if result.is_ok() { // commit
+            transaction.undo.clear();
+            transaction.unused_indices = None;
+        }
+
+        result // the transaction is dropped here, reverting if not committed
+    }
+}
+
+
+impl<'s, T: 's> Transaction<'s, T> {
+
+    /// Read-only access to the current state, including the modifications of this transaction
+    pub fn as_id_vec(&self) -> &IdVec<T> {
+        self.elements
+    }
+
+    /// Excludes deleted elements, and indices out of range
+    pub fn contains_id(&self, id: Id<T>) -> bool {
+        self.elements.contains_id(id)
+    }
+
+    /// Return a reference to the element that this id points to
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        self.elements.get(id)
+    }
+
+    /// Return a mutable reference to the element that this id points to.
+    /// A copy of the element is kept, in order to revert the modification.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> where T: Clone {
+        let previous = self.elements.get(id)?.clone();
+        self.undo.push(Change::Replace(id, previous));
+        self.elements.get_mut(id)
+    }
+
+    /// Associate the specified element with a currently unused id
+    pub fn insert(&mut self, element: T) -> Id<T> {
+        self.keep_unused_indices();
+        let id = self.elements.insert(element);
+        self.undo.push(Change::Remove(id));
+        id
+    }
+
+    /// Remove the element. The element is kept until the transaction is committed.
+    /// Returns false for invalid and deleted ids.
+    pub fn remove(&mut self, id: Id<T>) -> bool {
+        self.keep_unused_indices();
+        match self.elements.remove(id) {
+            Some(element) => {
+                self.undo.push(Change::Insert(id, element));
+                true
+            },
+
+            None => false,
+        }
+    }
+
+    /// Replace the element, without requiring it to be cloneable.
+    /// Panics on invalid Id parameter.
+    pub fn replace(&mut self, id: Id<T>, element: T) {
+        self.undo.push(Change::Replace(id, element).apply(self.elements));
+    }
+
+    /// Swap the elements pointed to. Panic on invalid Id parameter.
+    pub fn swap_elements(&mut self, id1: Id<T>, id2: Id<T>) {
+        self.elements.swap_elements(id1, id2);
+        self.undo.push(Change::Swap(id1, id2));
+    }
+
+    fn keep_unused_indices(&mut self) {
+        if self.unused_indices.is_none() {
+            self.unused_indices = Some(self.elements.unused_indices().clone());
+        }
+    }
+}
+
+impl<'s, T: 's> Drop for Transaction<'s, T> {
+    fn drop(&mut self) {
+        while let Some(change) = self.undo.pop() {
+            change.apply(self.elements);
+        }
+
+        if let Some(unused_indices) = self.unused_indices.take() {
+            self.elements.restore_unused_indices(unused_indices);
+        }
+    }
+}
+
+impl<'s, T: 's> ::std::ops::Index<Id<T>> for Transaction<'s, T> {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        &self.elements[id]
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn modified_vec() -> IdVec<&'static str> {
+        let mut vec = id_vec!("a", "b", "c", "d");
+        vec.remove(Id::from_index(1));
+        vec
+    }
+
+    fn unused_ids(vec: &IdVec<&'static str>) -> Vec<Index> {
+        (0 .. vec.id_index_limit()).filter(|&index| !vec.contains_id(Id::from_index(index))).collect()
+    }
+
+    #[test]
+    pub fn test_rollback_on_err(){
+        let mut vec = modified_vec();
+
+        let result: Result<(), &str> = vec.transaction(|tx| {
+            let e = tx.insert("e");
+            tx.insert("f");
+            tx.remove(Id::from_index(3));
+            tx.remove(Id::from_index(0));
+            tx.replace(Id::from_index(2), "x");
+            *tx.get_mut(e).unwrap() = "y";
+            tx.swap_elements(e, Id::from_index(2));
+            Err("failed halfway")
+        });
+
+        assert_eq!(result, Err("failed halfway"));
+        assert_eq!(vec, modified_vec());
+        assert_eq!(unused_ids(&vec), vec![1], "restoring the exact unused ids");
+        assert_eq!(vec.insert("e"), Id::from_index(1));
+    }
+
+    #[test]
+    pub fn test_rollback_keeps_reuse_order(){
+        let mut vec: IdVec<&'static str> = (0 .. 64).map(|_| "a").collect();
+        for index in (0 .. 63).filter(|index| index % 3 != 0) {
+            vec.remove(Id::from_index(index));
+        }
+
+        let mut untouched = vec.clone();
+
+        let result: Result<(), ()> = vec.transaction(|tx| {
+            for _ in 0 .. 30 { tx.insert("b"); }
+            for index in (0 .. 63).filter(|index| index % 3 == 0) { tx.remove(Id::from_index(index)); }
+            Err(())
+        });
+
+        assert!(result.is_err());
+        for _ in 0 .. 50 {
+            assert_eq!(vec.insert("c"), untouched.insert("c"), "reusing unused ids in the same order");
+        }
+    }
+
+    #[test]
+    pub fn test_commit_on_ok(){
+        let mut vec = modified_vec();
+
+        let id = vec.transaction(|tx| -> Result<_, ()> {
+            tx.remove(Id::from_index(3));
+            Ok(tx.insert("e"))
+        }).unwrap();
+
+        assert_eq!(vec[id], "e");
+        assert_eq!(vec.len(), 3);
+        assert!(!vec.contains_element(&"d"));
+    }
+
+    #[test]
+    pub fn test_rollback_on_panic(){
+        let mut vec = modified_vec();
+
+        let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            vec.transaction(|tx| -> Result<(), ()> {
+                tx.remove(Id::from_index(3));
+                tx.insert("e");
+                panic!("panicking inside transaction")
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(vec, modified_vec());
+        assert_eq!(unused_ids(&vec), vec![1]);
+    }
+}
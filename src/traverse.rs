@@ -0,0 +1,511 @@
+
+use ::std::collections::VecDeque;
+use ::id::*;
+use ::vec::IdVec;
+use ::read::ReadIdVec;
+use ::bits::BitSet;
+
+
+/// Implemented by elements which store the ids of other elements inside the same `IdVec`,
+/// for example a `parent: Option<Id<Node>>` field.
+/// Enables traversing an `IdVec<T>` like a graph.
+pub trait Neighbors: Sized {
+
+    /// Call `visit` for every id that this element points to.
+    /// Ids which are not contained in the `IdVec` are ignored by all algorithms.
+    fn visit_neighbors<F: FnMut(Id<Self>)>(&self, visit: F);
+}
+
+
+/// Collect the indices of all valid neighbors of an element, in the order of visiting
+fn valid_neighbors<T: Neighbors, V: ReadIdVec<T>>(vec: &V, element: Index) -> Vec<Index> {
+    let mut neighbors = Vec::new();
+
+    vec.get(Id::from_index(element)).expect("invalid element index").visit_neighbors(|neighbor| {
+        if vec.contains_id(neighbor) {
+            neighbors.push(neighbor.index_value());
+        }
+    });
+
+    neighbors
+}
+
+
+
+/// Visits all elements reachable from a start element, in breadth-first order.
+/// Each element is yielded exactly once, starting with the start element itself.
+pub struct Bfs<'s, T: 's, V: 's = IdVec<T>> {
+    vec: &'s V,
+    queue: VecDeque<Index>,
+    discovered: BitSet,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> Bfs<'s, T, V> {
+    pub fn new(vec: &'s V, start: Id<T>) -> Self {
+        let mut discovered = BitSet::new(vec.id_index_limit());
+        let mut queue = VecDeque::new();
+
+        if vec.contains_id(start) {
+            discovered.insert(start.index_value());
+            queue.push_back(start.index_value());
+        }
+
+        Bfs { vec, queue, discovered, _marker: ::std::marker::PhantomData }
+    }
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> Iterator for Bfs<'s, T, V> {
+    type Item = Id<T>;
+
+    fn next(&mut self) -> Option<Id<T>> {
+        self.queue.pop_front().map(|index| {
+            for neighbor in valid_neighbors(self.vec, index) {
+                if self.discovered.insert(neighbor) {
+                    self.queue.push_back(neighbor);
+                }
+            }
+
+            Id::from_index(index)
+        })
+    }
+}
+
+
+
+/// Visits all elements reachable from a start element, in depth-first pre-order.
+/// Each element is yielded exactly once, before any of its neighbors.
+pub struct Dfs<'s, T: 's, V: 's = IdVec<T>> {
+    vec: &'s V,
+    stack: Vec<Index>,
+    discovered: BitSet,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> Dfs<'s, T, V> {
+    pub fn new(vec: &'s V, start: Id<T>) -> Self {
+        let mut stack = Vec::new();
+        if vec.contains_id(start) {
+            stack.push(start.index_value());
+        }
+
+        Dfs { vec, stack, discovered: BitSet::new(vec.id_index_limit()), _marker: ::std::marker::PhantomData }
+    }
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> Iterator for Dfs<'s, T, V> {
+    type Item = Id<T>;
+
+    fn next(&mut self) -> Option<Id<T>> {
+        while let Some(index) = self.stack.pop() {
+            if self.discovered.insert(index) {
+                // push in reverse order, so that the first neighbor is visited first
+                for neighbor in valid_neighbors(self.vec, index).into_iter().rev() {
+                    if !self.discovered.contains(neighbor) {
+                        self.stack.push(neighbor);
+                    }
+                }
+
+                return Some(Id::from_index(index));
+            }
+        }
+
+        None
+    }
+}
+
+
+
+/// Visits all elements reachable from a start element, in depth-first post-order.
+/// Each element is yielded exactly once, after all of its neighbors
+/// (except for neighbors which are part of a cycle through that element).
+pub struct PostOrder<'s, T: 's, V: 's = IdVec<T>> {
+    vec: &'s V,
+    stack: Vec<Index>,
+    discovered: BitSet,
+    finished: BitSet,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> PostOrder<'s, T, V> {
+    pub fn new(vec: &'s V, start: Id<T>) -> Self {
+        let mut stack = Vec::new();
+        if vec.contains_id(start) {
+            stack.push(start.index_value());
+        }
+
+        PostOrder {
+            vec, stack,
+            discovered: BitSet::new(vec.id_index_limit()),
+            finished: BitSet::new(vec.id_index_limit()),
+            _marker: ::std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'s, T: 's + Neighbors, V: 's + ReadIdVec<T>> Iterator for PostOrder<'s, T, V> {
+    type Item = Id<T>;
+
+    fn next(&mut self) -> Option<Id<T>> {
+        while let Some(&index) = self.stack.last() {
+            if self.discovered.insert(index) {
+                for neighbor in valid_neighbors(self.vec, index).into_iter().rev() {
+                    if !self.discovered.contains(neighbor) {
+                        self.stack.push(neighbor);
+                    }
+                }
+
+            } else {
+                self.stack.pop();
+
+                // an element may have been pushed multiple times before being discovered
+                if self.finished.insert(index) {
+                    return Some(Id::from_index(index));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+
+
+/// Returned if the elements contain a cycle, where each element points to the next one,
+/// and the last element points to the first one again.
+// manually implementing clone, debug, eq, because T does not need to implement them
+pub struct Cycle<T> {
+    ids: Vec<Id<T>>,
+}
+
+impl<T> Cycle<T> {
+    /// An element which is part of the cycle
+    pub fn node(&self) -> Id<T> {
+        self.ids[0]
+    }
+
+    /// All elements of the cycle, in the order of the references
+    pub fn ids(&self) -> &[Id<T>] {
+        &self.ids
+    }
+
+    pub fn into_ids(self) -> Vec<Id<T>> {
+        self.ids
+    }
+}
+
+impl<T> Eq for Cycle<T> {}
+impl<T> PartialEq for Cycle<T> {
+    fn eq(&self, other: &Cycle<T>) -> bool {
+        self.ids == other.ids
+    }
+}
+impl<T> Clone for Cycle<T> {
+    fn clone(&self) -> Self {
+        Cycle { ids: self.ids.clone() }
+    }
+}
+impl<T> ::std::fmt::Debug for Cycle<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "Cycle{:?}", self.ids)
+    }
+}
+
+/// Prints the path of the cycle, for example `cycle detected: Id#0 -> Id#2 -> Id#0`
+impl<T> ::std::fmt::Display for Cycle<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "cycle detected: ")?;
+
+        for id in &self.ids {
+            write!(f, "{:?} -> ", id)?;
+        }
+
+        write!(f, "{:?}", self.node())
+    }
+}
+
+impl<T> ::std::error::Error for Cycle<T> {}
+
+
+
+/// Visit all elements depth-first, calling `finish` for each element after all of its neighbors.
+/// Aborts at the first back-reference to an element which has not been finished yet.
+fn visit_acyclic<T, V, F>(vec: &V, mut finish: F) -> Result<(), Cycle<T>>
+    where T: Neighbors, V: ReadIdVec<T>, F: FnMut(Id<T>)
+{
+    let mut on_stack = BitSet::new(vec.id_index_limit());
+    let mut finished = BitSet::new(vec.id_index_limit());
+
+    for (root, _) in vec.iter() {
+        if finished.contains(root.index_value()) {
+            continue;
+        }
+
+        // every element is on the stack together with its neighbors that have not been visited yet
+        on_stack.insert(root.index_value());
+        let mut stack = vec![ (root.index_value(), valid_neighbors(vec, root.index_value())) ];
+
+        while let Some(&mut (index, ref mut remaining)) = stack.last_mut() {
+            if let Some(neighbor) = remaining.pop() {
+                if on_stack.contains(neighbor) {
+                    // the stack contains the path from the neighbor to the current element
+                    let start = stack.iter().position(|&(index, _)| index == neighbor)
+                        .expect("element on stack not found");
+
+                    return Err(Cycle {
+                        ids: stack[start..].iter().map(|&(index, _)| Id::from_index(index)).collect()
+                    });
+                }
+
+                if !finished.contains(neighbor) {
+                    on_stack.insert(neighbor);
+                    stack.push((neighbor, valid_neighbors(vec, neighbor)));
+                }
+
+            } else {
+                stack.pop();
+                on_stack.remove(index);
+                finished.insert(index);
+                finish(Id::from_index(index));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Order all elements such that every element comes before the elements it points to.
+/// Fails if the elements contain a cycle, naming all elements of that cycle.
+/// Complexity of O(n + e), where e is the number of visited neighbors.
+pub fn topological_sort<T: Neighbors, V: ReadIdVec<T>>(vec: &V) -> Result<Vec<Id<T>>, Cycle<T>> {
+    let mut order = Vec::with_capacity(vec.len());
+    visit_acyclic(vec, |id| order.push(id))?;
+
+    order.reverse();
+    Ok(order)
+}
+
+/// Returns the path of any cycle, where each element points to the next one,
+/// and the last element points to the first one again.
+/// Complexity of O(n + e), where e is the number of visited neighbors.
+pub fn find_cycle<T: Neighbors, V: ReadIdVec<T>>(vec: &V) -> Option<Vec<Id<T>>> {
+    visit_acyclic(vec, |_| {}).err().map(Cycle::into_ids)
+}
+
+/// Returns false if any element can reach itself by following references.
+/// Complexity of O(n + e), where e is the number of visited neighbors.
+pub fn is_acyclic<T: Neighbors, V: ReadIdVec<T>>(vec: &V) -> bool {
+    visit_acyclic(vec, |_| {}).is_ok()
+}
+
+
+
+/// Group the elements such that all elements in a group can reach each other,
+/// using Tarjan's algorithm. Elements which are not part of a cycle form a group on their own.
+/// The groups are returned in reverse topological order:
+/// a group only points to groups which come before it.
+/// Complexity of O(n + e), where e is the number of visited neighbors.
+pub fn strongly_connected_components<T: Neighbors, V: ReadIdVec<T>>(vec: &V) -> Vec<Vec<Id<T>>> {
+    const UNVISITED: usize = usize::MAX;
+
+    let mut visit_order = vec![ UNVISITED; vec.id_index_limit() ];
+    let mut lowest_reachable = vec![ UNVISITED; vec.id_index_limit() ];
+    let mut on_stack = BitSet::new(vec.id_index_limit());
+    let mut component_stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_visit = 0;
+
+    for (root, _) in vec.iter() {
+        if visit_order[root.index_value()] != UNVISITED {
+            continue;
+        }
+
+        let mut stack: Vec<(Index, Vec<Index>)> = Vec::new();
+        let mut visit = root.index_value();
+
+        loop {
+            if visit != UNVISITED {
+                visit_order[visit] = next_visit;
+                lowest_reachable[visit] = next_visit;
+                next_visit += 1;
+
+                on_stack.insert(visit);
+                component_stack.push(visit);
+                stack.push((visit, valid_neighbors(vec, visit)));
+                visit = UNVISITED;
+            }
+
+            let (index, next_neighbor) = match stack.last_mut() {
+                Some(&mut (index, ref mut remaining)) => (index, remaining.pop()),
+                None => break,
+            };
+
+            if let Some(neighbor) = next_neighbor {
+                if visit_order[neighbor] == UNVISITED {
+                    visit = neighbor;
+
+                } else if on_stack.contains(neighbor) {
+                    lowest_reachable[index] = lowest_reachable[index].min(visit_order[neighbor]);
+                }
+
+            } else {
+                stack.pop();
+
+                if let Some(&(parent, _)) = stack.last() {
+                    lowest_reachable[parent] = lowest_reachable[parent].min(lowest_reachable[index]);
+                }
+
+                // this element is the root of a component, which consists of all elements above it
+                if lowest_reachable[index] == visit_order[index] {
+                    let start = component_stack.iter().rposition(|&element| element == index)
+                        .expect("component root not found");
+
+                    let component: Vec<Id<T>> = component_stack.drain(start..)
+                        .map(|element| { on_stack.remove(element); Id::from_index(element) })
+                        .collect();
+
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    components
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Node {
+        children: Vec<Id<Node>>,
+    }
+
+    impl Neighbors for Node {
+        fn visit_neighbors<F: FnMut(Id<Self>)>(&self, visit: F) {
+            self.children.iter().cloned().for_each(visit)
+        }
+    }
+
+    /// 0 -> 1 -> 3, 0 -> 2 -> 3, 4 -> 0
+    fn diamond() -> IdVec<Node> {
+        let id = Id::from_index;
+        id_vec!(
+            Node { children: vec![ id(1), id(2) ] },
+            Node { children: vec![ id(3) ] },
+            Node { children: vec![ id(3) ] },
+            Node { children: vec![] },
+            Node { children: vec![ id(0) ] }
+        )
+    }
+
+    fn indices<I: Iterator<Item = Id<Node>>>(ids: I) -> Vec<Index> {
+        ids.map(|id| id.index_value()).collect()
+    }
+
+    /// The indices of a cycle, starting at its smallest index, to compare cycles regardless of their start
+    fn rotated_to_smallest(cycle: &[Id<Node>]) -> Vec<Index> {
+        let mut indices = indices(cycle.iter().cloned());
+        let smallest = (0 .. indices.len()).min_by_key(|&position| indices[position]).unwrap_or(0);
+        indices.rotate_left(smallest);
+        indices
+    }
+
+    #[test]
+    pub fn test_bfs(){
+        let nodes = diamond();
+        assert_eq!(indices(Bfs::new(&nodes, Id::from_index(0))), vec![0, 1, 2, 3]);
+        assert_eq!(indices(Bfs::new(&nodes, Id::from_index(4))), vec![4, 0, 1, 2, 3]);
+        assert_eq!(indices(Bfs::new(&nodes, Id::from_index(9))), Vec::<Index>::new(), "invalid start");
+    }
+
+    #[test]
+    pub fn test_dfs(){
+        let nodes = diamond();
+        assert_eq!(indices(Dfs::new(&nodes, Id::from_index(4))), vec![4, 0, 1, 3, 2]);
+        assert_eq!(indices(PostOrder::new(&nodes, Id::from_index(4))), vec![3, 1, 2, 0, 4]);
+    }
+
+    #[test]
+    pub fn test_traversal_ignores_removed_elements(){
+        let mut nodes = diamond();
+        nodes.remove(Id::from_index(1));
+
+        assert_eq!(indices(Bfs::new(&nodes, Id::from_index(0))), vec![0, 2, 3]);
+        assert_eq!(indices(Dfs::new(&nodes, Id::from_index(0))), vec![0, 2, 3]);
+        assert_eq!(indices(PostOrder::new(&nodes, Id::from_index(0))), vec![3, 2, 0]);
+    }
+
+    #[test]
+    pub fn test_topological_sort(){
+        let mut nodes = diamond();
+        let order = indices(topological_sort(&nodes).unwrap().into_iter());
+
+        let position = |index: Index| order.iter().position(|&i| i == index).unwrap();
+        assert_eq!(order.len(), 5);
+        assert!(position(4) < position(0));
+        assert!(position(0) < position(1));
+        assert!(position(0) < position(2));
+        assert!(position(1) < position(3));
+        assert!(position(2) < position(3));
+
+        // create the only cycle 0 -> 2 -> 0
+        nodes[Id::from_index(2)].children.push(Id::from_index(0));
+        let cycle = topological_sort(&nodes).unwrap_err();
+        assert_eq!(rotated_to_smallest(cycle.ids()), vec![0, 2]);
+    }
+
+    #[test]
+    pub fn test_find_cycle(){
+        let mut nodes = diamond();
+        assert!(is_acyclic(&nodes));
+        assert_eq!(find_cycle(&nodes), None);
+
+        // create the only cycle 0 -> 2 -> 4 -> 0
+        nodes[Id::from_index(2)].children.push(Id::from_index(4));
+        assert!(!is_acyclic(&nodes));
+
+        let cycle = find_cycle(&nodes).unwrap();
+        assert_eq!(rotated_to_smallest(&cycle), vec![0, 2, 4]);
+        for (index, &id) in cycle.iter().enumerate() {
+            let next = cycle[(index + 1) % cycle.len()];
+            assert!(nodes[id].children.contains(&next), "each element points to the next one");
+        }
+
+        let error = topological_sort(&nodes).unwrap_err();
+        assert_eq!(error.ids(), &cycle[..]);
+        assert!(error.to_string().starts_with("cycle detected: Id#"));
+
+        // self-references are cycles with a single element
+        nodes[Id::from_index(2)].children.pop();
+        nodes[Id::from_index(3)].children = vec![ Id::from_index(3) ];
+        assert_eq!(find_cycle(&nodes), Some(vec![ Id::from_index(3) ]));
+    }
+
+    #[test]
+    pub fn test_strongly_connected_components(){
+        let mut nodes = diamond();
+        let components = strongly_connected_components(&nodes);
+        assert_eq!(components.len(), 5, "no cycles in diamond");
+        assert_eq!(components[0], vec![ Id::from_index(3) ], "reverse topological order");
+
+        // create the cycles 0 -> 1 -> 3 -> 4 -> 0 and 0 -> 2 -> 3 -> 4 -> 0, joining 0 to 4 into one component,
+        // and a separate node 5 which references itself
+        nodes[Id::from_index(3)].children.push(Id::from_index(4));
+        nodes.insert(Node { children: vec![ Id::from_index(5) ] });
+
+        let mut components: Vec<Vec<Index>> = strongly_connected_components(&nodes).into_iter()
+            .map(|component| {
+                let mut component = indices(component.into_iter());
+                component.sort();
+                component
+            })
+            .collect();
+
+        components.sort();
+        assert_eq!(components, vec![ vec![0, 1, 2, 3, 4], vec![5] ]);
+    }
+}
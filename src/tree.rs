@@ -0,0 +1,384 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Used as a key to access a node inside an `IdTree<T>`
+pub type NodeId<T> = Id<TreeNode<T>>;
+
+
+/// A value, together with the ids of its parent, its siblings and its first and last child.
+#[derive(Clone, Debug)]
+pub struct TreeNode<T> {
+    value: T,
+    parent: Option<NodeId<T>>,
+    previous_sibling: Option<NodeId<T>>,
+    next_sibling: Option<NodeId<T>>,
+    first_child: Option<NodeId<T>>,
+    last_child: Option<NodeId<T>>,
+}
+
+
+/// Stores a forest of values inside an `IdVec`,
+/// where each node knows its parent, its children and its siblings.
+/// A node without a parent is a root.
+#[derive(Clone, Debug)]
+pub struct IdTree<T> {
+    nodes: IdVec<TreeNode<T>>,
+}
+
+
+impl<T> IdTree<T> {
+
+    /// Does not allocate heap memory
+    pub fn new() -> Self {
+        IdTree { nodes: IdVec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Excludes removed nodes, and ids out of range
+    pub fn contains_id(&self, node: NodeId<T>) -> bool {
+        self.nodes.contains_id(node)
+    }
+
+
+
+    /// Insert a new root node without any children
+    pub fn insert(&mut self, value: T) -> NodeId<T> {
+        self.nodes.insert(TreeNode {
+            value, parent: None,
+            previous_sibling: None, next_sibling: None,
+            first_child: None, last_child: None,
+        })
+    }
+
+    /// Insert a new node as the last child of the parent.
+    /// Panics on invalid parent ids.
+    pub fn append_child(&mut self, parent: NodeId<T>, value: T) -> NodeId<T> {
+        assert!(self.contains_id(parent), "Appending child to invalid node `{:?}`", parent);
+
+        let child = self.insert(value);
+        self.attach(parent, child);
+        child
+    }
+
+    /// Move an existing node, together with all its descendants, to the end of the children of the parent.
+    /// Panics on invalid ids, and if the node is the parent itself or one of its ancestors.
+    pub fn append(&mut self, parent: NodeId<T>, node: NodeId<T>) {
+        assert!(self.contains_id(parent), "Appending to invalid node `{:?}`", parent);
+        assert!(self.contains_id(node), "Appending invalid node `{:?}`", node);
+        assert!(
+            parent != node && !self.ancestors(parent).any(|ancestor| ancestor == node),
+            "Appending `{:?}` to `{:?}` would create a cycle", node, parent
+        );
+
+        self.detach(node);
+        self.attach(parent, node);
+    }
+
+    /// Link a detached node as the last child of the parent
+    fn attach(&mut self, parent: NodeId<T>, child: NodeId<T>) {
+        let previous_last_child = self.nodes[parent].last_child;
+
+        {
+            let child = &mut self.nodes[child];
+            child.parent = Some(parent);
+            child.previous_sibling = previous_last_child;
+        }
+
+        match previous_last_child {
+            Some(previous) => self.nodes[previous].next_sibling = Some(child),
+            None => self.nodes[parent].first_child = Some(child),
+        }
+
+        self.nodes[parent].last_child = Some(child);
+    }
+
+    /// Unlink the node from its parent and siblings, making it a root.
+    /// Its descendants remain attached to the node.
+    /// Panics on invalid ids.
+    pub fn detach(&mut self, node: NodeId<T>) {
+        let (parent, previous, next) = {
+            let node = &mut self.nodes[node];
+            (node.parent.take(), node.previous_sibling.take(), node.next_sibling.take())
+        };
+
+        match previous {
+            Some(previous) => self.nodes[previous].next_sibling = next,
+            None => if let Some(parent) = parent { self.nodes[parent].first_child = next },
+        }
+
+        match next {
+            Some(next) => self.nodes[next].previous_sibling = previous,
+            None => if let Some(parent) = parent { self.nodes[parent].last_child = previous },
+        }
+    }
+
+    /// Remove the node and all of its descendants.
+    /// Returns the removed values, parents before children.
+    /// Make sure that no ids pointing to the removed nodes exist after this call.
+    /// Panics on invalid ids.
+    pub fn remove_subtree(&mut self, node: NodeId<T>) -> Vec<T> {
+        self.detach(node);
+
+        let subtree: Vec<NodeId<T>> = ::std::iter::once(node)
+            .chain(self.descendants(node)).collect();
+
+        subtree.into_iter()
+            .map(|id| self.nodes.remove(id).expect("descendant not found").value)
+            .collect()
+    }
+
+    /// Removes all nodes, instantly deallocating
+    pub fn clear(&mut self){
+        self.nodes.clear();
+    }
+
+
+
+    /// Return a reference to the value of the node that this id points to
+    pub fn get(&self, node: NodeId<T>) -> Option<&T> {
+        self.nodes.get(node).map(|node| &node.value)
+    }
+
+    /// Return a mutable reference to the value of the node that this id points to
+    pub fn get_mut(&mut self, node: NodeId<T>) -> Option<&mut T> {
+        self.nodes.get_mut(node).map(|node| &mut node.value)
+    }
+
+    /// Panics on invalid ids
+    pub fn parent(&self, node: NodeId<T>) -> Option<NodeId<T>> {
+        self.nodes[node].parent
+    }
+
+    /// Panics on invalid ids
+    pub fn first_child(&self, node: NodeId<T>) -> Option<NodeId<T>> {
+        self.nodes[node].first_child
+    }
+
+    /// Panics on invalid ids
+    pub fn last_child(&self, node: NodeId<T>) -> Option<NodeId<T>> {
+        self.nodes[node].last_child
+    }
+
+    /// Panics on invalid ids
+    pub fn previous_sibling(&self, node: NodeId<T>) -> Option<NodeId<T>> {
+        self.nodes[node].previous_sibling
+    }
+
+    /// Panics on invalid ids
+    pub fn next_sibling(&self, node: NodeId<T>) -> Option<NodeId<T>> {
+        self.nodes[node].next_sibling
+    }
+
+
+
+    /// Used for immutable access to all node ids and values, in no particular order
+    pub fn iter<'s>(&'s self) -> impl DoubleEndedIterator<Item = (NodeId<T>, &'s T)> + 's {
+        self.nodes.iter().map(|(id, node)| (id, &node.value))
+    }
+
+    /// All nodes without a parent
+    pub fn roots<'s>(&'s self) -> impl Iterator<Item = NodeId<T>> + 's {
+        self.nodes.iter()
+            .filter(|&(_, node)| node.parent.is_none())
+            .map(|(id, _)| id)
+    }
+
+    /// The parent, the parent of the parent, and so on, excluding the node itself.
+    /// Panics on invalid ids.
+    pub fn ancestors(&self, node: NodeId<T>) -> Ancestors<'_, T> {
+        Ancestors { tree: self, next: self.parent(node) }
+    }
+
+    /// The direct children of the node, from first to last.
+    /// Panics on invalid ids.
+    pub fn children(&self, node: NodeId<T>) -> FollowingSiblings<'_, T> {
+        FollowingSiblings { tree: self, next: self.first_child(node) }
+    }
+
+    /// The siblings after the node, excluding the node itself.
+    /// Panics on invalid ids.
+    pub fn following_siblings(&self, node: NodeId<T>) -> FollowingSiblings<'_, T> {
+        FollowingSiblings { tree: self, next: self.next_sibling(node) }
+    }
+
+    /// All nodes below the node in depth-first pre-order, excluding the node itself.
+    /// Panics on invalid ids.
+    pub fn descendants(&self, node: NodeId<T>) -> Descendants<'_, T> {
+        Descendants { tree: self, root: node, next: self.first_child(node) }
+    }
+}
+
+
+impl<T> Default for IdTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ::std::ops::Index<NodeId<T>> for IdTree<T> {
+    type Output = T;
+    fn index(&self, node: NodeId<T>) -> &T {
+        &self.nodes[node].value
+    }
+}
+
+impl<T> ::std::ops::IndexMut<NodeId<T>> for IdTree<T> {
+    fn index_mut(&mut self, node: NodeId<T>) -> &mut T {
+        &mut self.nodes[node].value
+    }
+}
+
+
+
+pub struct Ancestors<'s, T: 's> {
+    tree: &'s IdTree<T>,
+    next: Option<NodeId<T>>,
+}
+
+impl<'s, T: 's> Iterator for Ancestors<'s, T> {
+    type Item = NodeId<T>;
+
+    fn next(&mut self) -> Option<NodeId<T>> {
+        self.next.inspect(|&node| {
+            self.next = self.tree.parent(node);
+        })
+    }
+}
+
+
+pub struct FollowingSiblings<'s, T: 's> {
+    tree: &'s IdTree<T>,
+    next: Option<NodeId<T>>,
+}
+
+impl<'s, T: 's> Iterator for FollowingSiblings<'s, T> {
+    type Item = NodeId<T>;
+
+    fn next(&mut self) -> Option<NodeId<T>> {
+        self.next.inspect(|&node| {
+            self.next = self.tree.next_sibling(node);
+        })
+    }
+}
+
+
+pub struct Descendants<'s, T: 's> {
+    tree: &'s IdTree<T>,
+    root: NodeId<T>,
+    next: Option<NodeId<T>>,
+}
+
+impl<'s, T: 's> Iterator for Descendants<'s, T> {
+    type Item = NodeId<T>;
+
+    fn next(&mut self) -> Option<NodeId<T>> {
+        let current = self.next?;
+        let tree = self.tree;
+
+        // descend into the children first, then continue with the next sibling
+        // of the closest ancestor below the root which has one
+        self.next = tree.first_child(current).or_else(|| {
+            let mut node = current;
+            loop {
+                if node == self.root { return None; }
+                if let Some(next) = tree.next_sibling(node) { return Some(next); }
+                node = tree.parent(node).expect("descendant without parent");
+            }
+        });
+
+        Some(current)
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// root
+    ///   a
+    ///     a1
+    ///     a2
+    ///   b
+    ///     b1
+    fn scene() -> (IdTree<&'static str>, Vec<NodeId<&'static str>>) {
+        let mut tree = IdTree::new();
+        let root = tree.insert("root");
+        let a = tree.append_child(root, "a");
+        let a1 = tree.append_child(a, "a1");
+        let a2 = tree.append_child(a, "a2");
+        let b = tree.append_child(root, "b");
+        let b1 = tree.append_child(b, "b1");
+        (tree, vec![ root, a, a1, a2, b, b1 ])
+    }
+
+    fn values<I: Iterator<Item = NodeId<&'static str>>>(tree: &IdTree<&'static str>, ids: I) -> Vec<&'static str> {
+        ids.map(|id| tree[id]).collect()
+    }
+
+    #[test]
+    pub fn test_iterators(){
+        let (tree, ids) = scene();
+        let (root, a, a1, _a2, b, b1) = (ids[0], ids[1], ids[2], ids[3], ids[4], ids[5]);
+
+        assert_eq!(values(&tree, tree.children(root)), vec!["a", "b"]);
+        assert_eq!(values(&tree, tree.children(a1)), Vec::<&str>::new());
+        assert_eq!(values(&tree, tree.ancestors(b1)), vec!["b", "root"]);
+        assert_eq!(values(&tree, tree.following_siblings(a1)), vec!["a2"]);
+        assert_eq!(values(&tree, tree.following_siblings(b)), Vec::<&str>::new());
+        assert_eq!(values(&tree, tree.descendants(root)), vec!["a", "a1", "a2", "b", "b1"]);
+        assert_eq!(values(&tree, tree.descendants(a)), vec!["a1", "a2"], "stops at subtree root");
+        assert_eq!(values(&tree, tree.roots()), vec!["root"]);
+        assert_eq!(tree.previous_sibling(b), Some(a));
+    }
+
+    #[test]
+    pub fn test_detach_and_append(){
+        let (mut tree, ids) = scene();
+        let (root, a, a1, a2, b, _b1) = (ids[0], ids[1], ids[2], ids[3], ids[4], ids[5]);
+
+        tree.detach(a1);
+        assert_eq!(tree.parent(a1), None);
+        assert_eq!(tree.first_child(a), Some(a2));
+        assert_eq!(tree.previous_sibling(a2), None);
+        assert_eq!(values(&tree, tree.roots()), vec!["root", "a1"]);
+
+        tree.append(b, a);
+        assert_eq!(values(&tree, tree.children(root)), vec!["b"]);
+        assert_eq!(values(&tree, tree.children(b)), vec!["b1", "a"]);
+        assert_eq!(values(&tree, tree.descendants(root)), vec!["b", "b1", "a", "a2"]);
+        assert_eq!(tree.last_child(b), Some(a));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_append_to_descendant(){
+        let (mut tree, ids) = scene();
+        tree.append(ids[2], ids[0]);
+    }
+
+    #[test]
+    pub fn test_remove_subtree(){
+        let (mut tree, ids) = scene();
+        let (root, a, a1, _a2, b, _b1) = (ids[0], ids[1], ids[2], ids[3], ids[4], ids[5]);
+
+        assert_eq!(tree.remove_subtree(a), vec!["a", "a1", "a2"]);
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.contains_id(a1));
+        assert_eq!(tree.first_child(root), Some(b));
+        assert_eq!(values(&tree, tree.descendants(root)), vec!["b", "b1"]);
+
+        assert_eq!(tree.remove_subtree(root), vec!["root", "b", "b1"]);
+        assert!(tree.is_empty());
+    }
+}
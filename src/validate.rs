@@ -0,0 +1,139 @@
+
+use ::id::*;
+use ::vec::IdVec;
+
+
+/// Implemented by elements which store the ids of other elements inside the same `IdVec`.
+/// Enables detecting ids which point to removed elements.
+pub trait References: Sized {
+
+    /// Call `visit` for every id stored inside this element,
+    /// together with the path of the field containing it, for example `"children[2]"`.
+    fn visit_references<F: FnMut(&str, Id<Self>)>(&self, visit: F);
+}
+
+
+/// An id stored inside an element, which does not point to any element of the `IdVec`
+// manually implementing clone, debug, eq, because T does not need to implement them
+pub struct DanglingReference<T> {
+    /// The element containing the invalid id
+    pub owner: Id<T>,
+
+    /// The path of the field inside the owner element
+    pub field: String,
+
+    /// The invalid id
+    pub target: Id<T>,
+}
+
+
+impl<T> IdVec<T> where T: References {
+
+    /// Find all ids stored inside the elements which are not contained in this IdVec.
+    /// Complexity of O(n + r), where r is the number of stored ids.
+    pub fn validate_references(&self) -> Result<(), Vec<DanglingReference<T>>> {
+        let mut dangling = Vec::new();
+
+        for (owner, element) in self.iter() {
+            element.visit_references(|field, target| {
+                if !self.contains_id(target) {
+                    dangling.push(DanglingReference { owner, field: field.to_owned(), target });
+                }
+            });
+        }
+
+        if dangling.is_empty() { Ok(()) } else { Err(dangling) }
+    }
+
+    /// Panics if any element contains an invalid id, but only in debug builds.
+    /// Intended to be called after a series of modifications.
+    #[inline]
+    pub fn debug_assert_references(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(dangling) = self.validate_references() {
+                panic!("IdVec contains {} dangling references: {:?}", dangling.len(), dangling);
+            }
+        }
+    }
+}
+
+
+impl<T> Eq for DanglingReference<T> {}
+impl<T> PartialEq for DanglingReference<T> {
+    fn eq(&self, other: &DanglingReference<T>) -> bool {
+        self.owner == other.owner && self.field == other.field && self.target == other.target
+    }
+}
+impl<T> Clone for DanglingReference<T> {
+    fn clone(&self) -> Self {
+        DanglingReference { owner: self.owner, field: self.field.clone(), target: self.target }
+    }
+}
+impl<T> ::std::fmt::Debug for DanglingReference<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "{:?}.{} -> {:?}", self.owner, self.field, self.target)
+    }
+}
+impl<T> ::std::fmt::Display for DanglingReference<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "field `{}` of {:?} points to missing element {:?}", self.field, self.owner, self.target)
+    }
+}
+impl<T> ::std::error::Error for DanglingReference<T> {}
+
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Node {
+        parent: Option<Id<Node>>,
+        children: Vec<Id<Node>>,
+    }
+
+    impl References for Node {
+        fn visit_references<F: FnMut(&str, Id<Self>)>(&self, mut visit: F) {
+            if let Some(parent) = self.parent {
+                visit("parent", parent);
+            }
+
+            for (index, &child) in self.children.iter().enumerate() {
+                visit(&format!("children[{}]", index), child);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_validate_references(){
+        let mut nodes = IdVec::new();
+        let root = nodes.insert(Node { parent: None, children: vec![] });
+        let a = nodes.insert(Node { parent: Some(root), children: vec![] });
+        let b = nodes.insert(Node { parent: Some(root), children: vec![] });
+        nodes[root].children = vec![ a, b ];
+
+        assert!(nodes.validate_references().is_ok());
+        nodes.debug_assert_references();
+
+        nodes.remove(a);
+        let dangling = nodes.validate_references().unwrap_err();
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].owner, root);
+        assert_eq!(dangling[0].field, "children[0]");
+        assert_eq!(dangling[0].target, a);
+        assert_eq!(dangling[0].to_string(), "field `children[0]` of Id#0 points to missing element Id#1");
+
+        nodes.remove(root);
+        let dangling = nodes.validate_references().unwrap_err();
+        assert_eq!(dangling.iter().map(|reference| reference.owner).collect::<Vec<_>>(), vec![ b ]);
+        assert_eq!(dangling[0].field, "parent");
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    pub fn test_debug_assert_references(){
+        let nodes = id_vec!(Node { parent: Some(Id::from_index(3)), children: vec![] });
+        nodes.debug_assert_references();
+    }
+}
@@ -1,6 +1,7 @@
 
 use ::std::collections::HashSet;
 use ::id::*;
+use ::changes::Changes;
 
 
 /// Create a new id_vec by entering a series of values
@@ -19,14 +20,18 @@ macro_rules! id_vec {
 /// but a packed IdVec will append elements to the end of the internal vector.
 #[derive(Clone, Default)] // manual impl: Eq, PartialEq
 pub struct IdVec<T> {
-    /// Packed dense vector, containing alive and dead elements.
+    /// Packed dense vector, containing used and unused slots.
+    /// Unused slots are `None`, so removed elements are dropped immediately.
     /// Because removing the last element directly can be done efficiently,
     /// it is guaranteed that the last element is never unused.
-    elements: Vec<T>,
+    elements: Vec<Option<T>>,
 
     /// Contains all unused ids which are allowed to be overwritten,
     /// will never contain the last used ID, because the last id can be removed directly
     unused_indices: HashSet<Index>, // TODO if iteration is too slow, use both Vec<NextUnusedIndex> and BitVec
+
+    /// The ids changed since the last `take_changes()`, only if tracking is enabled
+    changes: Option<Changes<T>>,
 }
 
 
@@ -40,16 +45,19 @@ impl<T> IdVec<T> {
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
-        Self::from(Vec::with_capacity(capacity))
+        IdVec {
+            elements: Vec::with_capacity(capacity),
+            unused_indices: HashSet::new(),
+            changes: None,
+        }
     }
 
-    /// Create a map containing these elements.
-    /// Directly uses the specified vector,
-    /// so no heap allocation is made calling this function.
+    /// Create a map containing these elements, with ids equal to the vector indices.
     pub fn from_vec(elements: Vec<T>) -> Self {
         IdVec {
             unused_indices: HashSet::new(), // no elements deleted
-            elements,
+            elements: elements.into_iter().map(Some).collect(),
+            changes: None,
         }
     }
 
@@ -116,32 +124,69 @@ impl<T> IdVec<T> {
         self.unused_indices.is_empty()
     }
 
+    /// Start recording which ids are inserted, removed or mutably accessed.
+    /// Does nothing if changes are already being tracked.
+    pub fn track_changes(&mut self) {
+        if self.changes.is_none() {
+            self.changes = Some(Changes::new());
+        }
+    }
+
+    /// Stop recording changes, discarding all changes which have not been taken yet
+    pub fn stop_tracking_changes(&mut self) {
+        self.changes = None;
+    }
+
+    pub fn is_tracking_changes(&self) -> bool {
+        self.changes.is_some()
+    }
+
+    /// Return all changes since the last call, starting a new tick.
+    /// Returns no changes if tracking is disabled.
+    pub fn take_changes(&mut self) -> Changes<T> {
+        self.changes.as_mut().map(::std::mem::take).unwrap_or_default()
+    }
+
+    fn record_change<F>(&mut self, record: F) where F: FnOnce(&mut Changes<T>) {
+        if let Some(ref mut changes) = self.changes {
+            record(changes);
+        }
+    }
+
 
 
     /// Enable the specified id to be overwritten when a new element is inserted.
-    /// This does not directly deallocate the element.
+    /// Returns the removed element.
     /// Make sure that no ids pointing to that element exist after this call.
-    /// Ignores invalid and deleted ids.
-    pub fn remove(&mut self, element: Id<T>) {
+    /// Ignores invalid and deleted ids, returning `None`.
+    pub fn remove(&mut self, element: Id<T>) -> Option<T> {
         self.debug_assert_last_element_is_used();
 
-        if self.index_is_in_range(element.index_value()) {
+        let removed = if self.contains_id(element) {
 
             // if exactly the last element, remove without inserting into unused_ids
             if element.index_value() + 1 == self.elements.len() {
-                self.elements.pop();
+                let removed = self.elements.pop().and_then(|slot| slot);
 
                 // remove all unused elements at the end of the vector
                 // which may have been guarded by the (now removed) last element
                 self.pop_back_unused();
+                removed
 
             } else { // remove not-the-last element
-                self.unused_indices.insert(element.index_value()); // may overwrite existing index
+                self.unused_indices.insert(element.index_value());
+                self.elements[element.index_value()].take()
             }
+
+        } else { None };
+
+        if removed.is_some() {
+            self.record_change(|changes| changes.record_remove(element));
         }
 
         self.debug_assert_id_validity(element, false);
         self.debug_assert_last_element_is_used();
+        removed
     }
 
     /// Removes an id and the associated element.
@@ -150,9 +195,13 @@ impl<T> IdVec<T> {
         self.debug_assert_last_element_is_used();
 
         let popped = self.elements.pop().map(|element|{
-            (Id::from_index(self.elements.len()), element)
+            (Id::from_index(self.elements.len()), element.expect("Last element of IdMap is not used"))
         });
 
+        if let Some((id, _)) = popped {
+            self.record_change(|changes| changes.record_remove(id));
+        }
+
         self.pop_back_unused();
         popped
     }
@@ -182,56 +231,108 @@ impl<T> IdVec<T> {
     }
 
     /// Associate the specified element with a currently unused id.
-    /// This may overwrite (thus drop) unused elements.
     pub fn insert(&mut self, element: T) -> Id<T> {
-        let id = Id::from_index({
-            if let Some(previously_unused_index) = self.unused_indices.iter().next().map(|i| *i) {
-                self.debug_assert_id_validity(Id::from_index(previously_unused_index), false);
-                self.unused_indices.remove(&previously_unused_index);
-                self.elements[previously_unused_index] = element;
-                previously_unused_index
-            } else {
-                self.elements.push(element);
-                self.elements.len() - 1
+        let id = self.next_insert_id();
+        self.debug_assert_id_validity(id, false);
+        self.insert_at(id, element);
+        id
+    }
+
+    /// The id that the next call to `insert` will return
+    pub(crate) fn next_insert_id(&self) -> Id<T> {
+        let previously_unused_index = self.unused_indices.iter().next().copied();
+        Id::from_index(previously_unused_index.unwrap_or(self.elements.len()))
+    }
+
+    /// The unused ids, iterated in the order in which `insert` reuses them
+    pub(crate) fn unused_indices(&self) -> &HashSet<Index> {
+        &self.unused_indices
+    }
+
+    /// Replace the unused ids with a copy containing the same ids, restoring the order in which they are reused
+    pub(crate) fn restore_unused_indices(&mut self, unused_indices: HashSet<Index>) {
+        debug_assert!(unused_indices == self.unused_indices, "Restoring different unused ids");
+        self.unused_indices = unused_indices;
+    }
+
+    /// Associate the element with exactly the specified id.
+    /// Returns the previous element if the id was already used.
+    /// If the id is out of range, all slots in between are marked as unused.
+    pub fn insert_at(&mut self, id: Id<T>, element: T) -> Option<T> {
+        let index = id.index_value();
+
+        let previous = if index < self.elements.len() {
+            self.unused_indices.remove(&index);
+            self.elements[index].replace(element)
+
+        } else {
+            for unused_index in self.elements.len() .. index {
+                self.unused_indices.insert(unused_index);
+                self.elements.push(None);
             }
-        });
+
+            self.elements.push(Some(element));
+            None
+        };
+
+        if previous.is_some() {
+            self.record_change(|changes| changes.record_modify(id));
+        } else {
+            self.record_change(|changes| changes.record_insert(id));
+        }
 
         self.debug_assert_last_element_is_used();
         self.debug_assert_id_validity(id, true);
-        id
+        previous
     }
 
 
-
     /// Return a reference to the element that this id points to
     pub fn get(&self, element: Id<T>) -> Option<&T> {
-        if self.index_is_currently_used(element.index_value()) {
-            self.elements.get(element.index_value())
-        } else { None }
+        self.elements.get(element.index_value()).and_then(Option::as_ref)
     }
 
-    /// Return a mutable reference to the element that this id points to
-    pub fn get_mut<'s>(&'s mut self, element: Id<T>) -> Option<&'s mut T> {
-        if self.index_is_currently_used(element.index_value()) {
-            self.elements.get_mut(element.index_value())
-        } else { None }
+    /// Return a mutable reference to the element that this id points to.
+    /// The id is recorded as modified if changes are tracked.
+    pub fn get_mut(&mut self, element: Id<T>) -> Option<&mut T> {
+        if self.contains_id(element) {
+            self.record_change(|changes| changes.record_modify(element));
+        }
+
+        self.elements.get_mut(element.index_value()).and_then(Option::as_mut)
     }
 
 
     /// Swap the elements pointed to. Panic on invalid Id parameter.
     pub fn swap_elements(&mut self, id1: Id<T>, id2: Id<T>){
-        self.debug_assert_id_validity(id1, true);
-        self.debug_assert_id_validity(id2, true);
+        // a release assertion, as swapping with an unused slot would move `None` into a used id
+        assert!(self.contains_id(id1) && self.contains_id(id2), "Swapping invalid Ids: `{:?}` and `{:?}`", id1, id2);
         self.elements.swap(id1.index_value(), id2.index_value());
+
+        self.record_change(|changes| {
+            changes.record_modify(id1);
+            changes.record_modify(id2);
+        });
     }
 
     /// Removes all elements, instantly deallocating
     pub fn clear(&mut self){
+        self.record_all_removed();
         self.elements.clear();
         self.unused_indices.clear();
         debug_assert!(self.is_empty());
     }
 
+    fn record_all_removed(&mut self){
+        if let Some(mut changes) = self.changes.take() {
+            for id in self.ids() {
+                changes.record_remove(id);
+            }
+
+            self.changes = Some(changes);
+        }
+    }
+
     /// Shrinks the internal vector itself
     pub fn shrink_to_fit(&mut self){
         self.elements.shrink_to_fit();
@@ -248,10 +349,13 @@ impl<T> IdVec<T> {
     pub fn retain<F>(&mut self, predicate: F) where F: Fn(Id<T>, &T) -> bool {
         for index in 0..self.elements.len() {
             let id = Id::from_index(index);
-            if !self.unused_indices.contains(&index)
-                && !predicate(id, &self.elements[index])
-            {
+            let retained = self.elements[index].as_ref()
+                .is_none_or(|element| predicate(id, element));
+
+            if !retained {
+                self.elements[index] = None;
                 self.unused_indices.insert(index);
+                self.record_change(|changes| changes.record_remove(id));
             }
         }
 
@@ -262,9 +366,8 @@ impl<T> IdVec<T> {
     /// and calling remap(old_id, new_id) for every element that has been moved to a new Id
     /// It does not preserve order of the inserted items.
     pub fn pack<F>(&mut self, mut remap: F) where F: FnMut(Id<T>, Id<T>) {
-        let mut unused_indices = ::std::mem::replace(
-            &mut self.unused_indices,
-            HashSet::new() // does not allocate
+        let mut unused_indices = ::std::mem::take(
+            &mut self.unused_indices // does not allocate
         );
 
         while let Some(&unused_index) = unused_indices.iter().next() {
@@ -276,6 +379,11 @@ impl<T> IdVec<T> {
                 self.elements.swap(last_used_element_index, unused_index);
                 remap(Id::from_index(last_used_element_index), Id::from_index(unused_index));
 
+                self.record_change(|changes| {
+                    changes.record_insert(Id::from_index(unused_index));
+                    changes.record_remove(Id::from_index(last_used_element_index));
+                });
+
                 // pop the (last, unused) element
                 unused_indices.remove(&unused_index); // must be updated to avoid popping already swapped elements
                 self.elements.pop();
@@ -300,26 +408,30 @@ impl<T> IdVec<T> {
         }
     }
 
-    // pub fn iter_mut<'s>(&'s mut self) -> IterMut cannot be implemented safely
-    // because it would require multiple mutable references
+    /// Used for mutable access to ids and elements.
+    /// Every yielded id is recorded as modified if changes are tracked.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            iter: self.elements.iter_mut().enumerate(),
+            changes: self.changes.as_mut(),
+        }
+    }
 
     /// Iterate over the elements, consuming this IdVec
     pub fn into_elements(self) -> IntoElements<T> {
         IntoElements {
-            exclusive_max_index: self.elements.len(),
-            unused_ids: self.unused_indices,
+            remaining: self.len(),
             iter: self.elements.into_iter(),
-            next_index: 0,
         }
     }
 
     /// Iterate over the elements, clearing this IdVec
-    pub fn drain_elements(&mut self) -> DrainElements<T> {
+    pub fn drain_elements(&mut self) -> DrainElements<'_, T> {
+        self.record_all_removed();
         DrainElements {
-            exclusive_max_index: self.elements.len(),
+            remaining: self.len(),
             unused_ids: &mut self.unused_indices,
             iter: self.elements.drain(..),
-            next_index: 0,
         }
     }
 
@@ -404,15 +516,13 @@ impl<T> From<Vec<T>> for IdVec<T> {
 impl<T> ::std::ops::Index<Id<T>> for IdVec<T> {
     type Output = T;
     fn index(&self, element: Id<T>) -> &T {
-        debug_assert!(self.contains_id(element), "Indexing with invalid Id: `{:?}` ", element);
-        &self.elements[element.index_value()]
+        self.get(element).unwrap_or_else(|| panic!("Indexing with invalid Id: `{:?}` ", element))
     }
 }
 
 impl<T> ::std::ops::IndexMut<Id<T>> for IdVec<T> {
     fn index_mut(&mut self, element: Id<T>) -> &mut T {
-        debug_assert!(self.contains_id(element), "Indexing-Mut with invalid Id: `{:?}` ", element);
-        &mut self.elements[element.index_value()]
+        self.get_mut(element).unwrap_or_else(|| panic!("Indexing-Mut with invalid Id: `{:?}` ", element))
     }
 }
 
@@ -522,7 +632,7 @@ impl<'s, T: 's> Iterator for Iter<'s, T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         let max_remaining = self.exclusive_back_index - self.inclusive_front_index;
         let unused_elements = self.storage.unused_indices.len();
-        let min_remaining = max_remaining.checked_sub(unused_elements).unwrap_or(0);
+        let min_remaining = max_remaining.saturating_sub(unused_elements);
         (min_remaining, Some(max_remaining))
     }
 }
@@ -542,6 +652,49 @@ impl<'s, T: 's> DoubleEndedIterator for Iter<'s, T> {
 
 
 
+pub struct IterMut<'s, T: 's> {
+    iter: ::std::iter::Enumerate<::std::slice::IterMut<'s, Option<T>>>,
+    changes: Option<&'s mut Changes<T>>,
+}
+
+impl<'s, T: 's> IterMut<'s, T> {
+    fn record(&mut self, index: Index, element: &'s mut T) -> (Id<T>, &'s mut T) {
+        let id = Id::from_index(index);
+        if let Some(ref mut changes) = self.changes {
+            changes.record_modify(id);
+        }
+
+        (id, element)
+    }
+}
+
+impl<'s, T: 's> Iterator for IterMut<'s, T> {
+    type Item = (Id<T>, &'s mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // skip deleted elements
+        let (index, element) = self.iter.by_ref()
+            .find_map(|(index, slot)| slot.as_mut().map(|element| (index, element)))?;
+
+        Some(self.record(index, element))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<'s, T: 's> DoubleEndedIterator for IterMut<'s, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (index, element) = self.iter.by_ref().rev()
+            .find_map(|(index, slot)| slot.as_mut().map(|element| (index, element)))?;
+
+        Some(self.record(index, element))
+    }
+}
+
+
+
 pub struct ElementIter<'s, T: 's> {
     iter: Iter<'s, T>,
 }
@@ -565,12 +718,9 @@ impl<'s, T: 's> DoubleEndedIterator for ElementIter<'s, T> {
 }
 
 
-/// Note: always iterates backwards, because it just calls IdMap.pop()
 pub struct IntoElements<T> {
-    iter: ::std::vec::IntoIter<T>,
-    unused_ids: HashSet<Index>,
-    exclusive_max_index: Index,
-    next_index: Index,
+    iter: ::std::vec::IntoIter<Option<T>>,
+    remaining: usize,
 }
 
 impl<T> ExactSizeIterator for IntoElements<T> {}
@@ -578,34 +728,21 @@ impl<T> Iterator for IntoElements<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.unused_ids.remove(&self.next_index) {
-            self.next_index += 1;
-            self.iter.next().unwrap(); // skip deleted element
-        }
-
-        if self.next_index < self.exclusive_max_index {
-            self.next_index += 1;
-            Some(self.iter.next().unwrap())
-
-        } else {
-            None
-        }
+        let next = self.iter.by_ref().flatten().next(); // skip deleted elements
+        if next.is_some() { self.remaining -= 1; }
+        next
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let elements = self.exclusive_max_index - self.next_index;
-        let used = elements - self.unused_ids.len(); // self.unused_ids is updated on self.next()
-        (used, Some(used))
+        (self.remaining, Some(self.remaining))
     }
 }
 
 
-/// Note: always iterates backwards, because it just calls IdMap.pop()
 pub struct DrainElements<'s, T: 's> {
-    iter: ::std::vec::Drain<'s, T>,
+    iter: ::std::vec::Drain<'s, Option<T>>,
     unused_ids: &'s mut HashSet<Index>,
-    exclusive_max_index: Index,
-    next_index: Index,
+    remaining: usize,
 }
 
 impl<'s, T: 's> ExactSizeIterator for DrainElements<'s, T> {}
@@ -613,24 +750,13 @@ impl<'s, T: 's> Iterator for DrainElements<'s, T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.unused_ids.remove(&self.next_index) {
-            self.next_index += 1;
-            self.iter.next().unwrap(); // skip deleted element
-        }
-
-        if self.next_index < self.exclusive_max_index {
-            self.next_index += 1;
-            Some(self.iter.next().unwrap())
-
-        } else {
-            None
-        }
+        let next = self.iter.by_ref().flatten().next(); // skip deleted elements
+        if next.is_some() { self.remaining -= 1; }
+        next
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let elements = self.exclusive_max_index - self.next_index;
-        let used = elements - self.unused_ids.len(); // self.unused_ids is updated on self.next()
-        (used, Some(used))
+        (self.remaining, Some(self.remaining))
     }
 }
 
@@ -694,7 +820,7 @@ impl<T> Iterator for OwnedIdIter<T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         let max_remaining = self.exclusive_back_index - self.inclusive_front_index;
         let unused_elements = self.unused_ids.len();
-        let min_remaining = max_remaining.checked_sub(unused_elements).unwrap_or(0);
+        let min_remaining = max_remaining.saturating_sub(unused_elements);
         (min_remaining, Some(max_remaining))
     }
 }
@@ -730,20 +856,20 @@ mod test {
     pub fn test_from_iterator(){
         let vec = vec![0, 1, 2, 5];
         let map = vec.into_iter().collect::<IdVec<_>>();
-        assert_eq!(map.elements, vec![0, 1, 2, 5]);
+        assert_eq!(map.elements, vec![Some(0), Some(1), Some(2), Some(5)]);
     }
 
     #[test]
     pub fn test_from_vec(){
         let vec = vec![0, 1, 2, 5];
         let map = IdVec::from_vec(vec);
-        assert_eq!(map.elements, vec![0, 1, 2, 5]);
+        assert_eq!(map.elements, vec![Some(0), Some(1), Some(2), Some(5)]);
     }
 
     #[test]
     pub fn test_from_macro(){
         let map = id_vec!(0, 1, 2, 5);
-        assert_eq!(map.elements, vec![0, 1, 2, 5]);
+        assert_eq!(map.elements, vec![Some(0), Some(1), Some(2), Some(5)]);
     }
 
     #[test]
@@ -794,6 +920,34 @@ mod test {
         }
     }
 
+    #[test]
+    pub fn test_remove_returns_element(){
+        let mut map = id_vec!(0, 2, 5);
+        assert_eq!(map.remove(Id::from_index(1)), Some(2));
+        assert_eq!(map.remove(Id::from_index(1)), None, "removing deleted id");
+        assert_eq!(map.remove(Id::from_index(7)), None, "removing id out of range");
+        assert_eq!(map.remove(Id::from_index(2)), Some(5));
+        assert_eq!(map.elements, vec![Some(0)], "removing unused elements at the back");
+    }
+
+    #[test]
+    pub fn test_insert_at(){
+        let mut map = id_vec!(0, 1, 2);
+        map.remove(Id::from_index(1));
+
+        assert_eq!(map.insert_at(Id::from_index(1), 4), None, "inserting into unused slot");
+        assert!(map.is_packed());
+        assert_eq!(map.insert_at(Id::from_index(1), 5), Some(4), "replacing used slot");
+
+        assert_eq!(map.insert_at(Id::from_index(5), 6), None, "inserting out of range");
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.ids().map(|id| id.index_value()).collect::<Vec<_>>(), vec![0, 1, 2, 5]);
+
+        map.remove(Id::from_index(5));
+        assert!(map.is_packed(), "out of range slots are unused");
+        assert_eq!(map.elements.len(), 3);
+    }
+
     #[test]
     pub fn test_pop(){
         let mut map = id_vec!(0, 2, 5);
@@ -815,7 +969,8 @@ mod test {
     #[test]
     pub fn test_into_iterator(){
         let map = IdVec {
-            elements: vec![0, 2, 3, 4],
+            elements: vec![Some(0), Some(2), Some(3), Some(4)],
+            changes: None,
             unused_indices: HashSet::new(),
         };
 
@@ -1028,7 +1183,15 @@ mod test {
             Id::from_index(1),
         );
 
-        assert_eq!(map.elements, vec![2, 1, 3]);
+        assert_eq!(map.elements, vec![Some(2), Some(1), Some(3)]);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_swap_with_unused_slot(){
+        let mut map = id_vec!(1,2,3);
+        map.remove(Id::from_index(1));
+        map.swap_elements(Id::from_index(0), Id::from_index(1));
     }
 
 
@@ -1108,6 +1271,57 @@ mod test {
     }
 
 
+    #[test]
+    pub fn test_iter_mut(){
+        let mut map = id_vec!(0, 1, 2, 3);
+        map.remove(Id::from_index(1));
+
+        for (_, element) in map.iter_mut() {
+            *element *= 10;
+        }
+
+        assert_eq!(map.iter_mut().next_back().map(|(id, _)| id), Some(Id::from_index(3)));
+        assert_eq!(map.elements().cloned().collect::<Vec<_>>(), vec![0, 20, 30]);
+    }
+
+    #[test]
+    pub fn test_track_changes(){
+        let mut map = id_vec!(0, 1, 2, 3);
+        assert!(map.take_changes().is_empty(), "not tracking");
+
+        map.track_changes();
+        let id_4 = map.insert(4);
+        map.remove(Id::from_index(1));
+        map[Id::from_index(2)] = 20;
+        for (id, element) in map.iter_mut() {
+            if id == Id::from_index(3) { *element = 30; }
+        }
+
+        let changes = map.take_changes();
+        assert_eq!(changes.inserted, [id_4].iter().cloned().collect());
+        assert_eq!(changes.removed, [Id::from_index(1)].iter().cloned().collect());
+        assert!(changes.modified.contains(&Id::from_index(2)));
+        assert!(!changes.modified.contains(&Id::from_index(1)), "removed elements are not modified");
+        assert!(!changes.modified.contains(&id_4), "inserted elements are not modified");
+        assert!(map.take_changes().is_empty(), "taking changes starts a new tick");
+
+        let id_5 = map.insert(5);
+        map.remove(id_5);
+        map.remove(Id::from_index(0));
+        let reused = Id::from_index(0);
+        map.insert_at(reused, 6);
+        let changes = map.take_changes();
+        assert!(changes.inserted.is_empty(), "inserted and removed in the same tick");
+        assert_eq!(changes.modified, [reused].iter().cloned().collect(), "removed and reinserted in the same tick");
+
+        map.clear();
+        assert_eq!(map.take_changes().removed.len(), 4);
+
+        map.stop_tracking_changes();
+        map.insert(7);
+        assert!(map.take_changes().is_empty());
+    }
+
 
 
     // TODO test repeated random removing and inserting